@@ -0,0 +1,49 @@
+//! Test module run natively on the host (`std` feature).
+//!
+//! ```text
+//! cargo run --example host --no-default-features --features std,autorun
+//! ```
+#[bern_test::tests]
+mod tests {
+    #[test]
+    fn passes() {
+        assert_eq!(2u32.pow(2), 4);
+    }
+
+    #[test]
+    fn panics() {
+        assert_eq!(2u32.pow(2), 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_panic() {
+        panic!("expected");
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn expected_message() {
+        panic!("value out of range");
+    }
+
+    #[test]
+    #[should_panic(expected = "host.rs")]
+    fn expected_location() {
+        /* the location is not part of the message */
+        panic!("unrelated");
+    }
+
+    #[test]
+    fn returns_error() -> Result<(), &'static str> {
+        Err("sensor timeout")
+    }
+
+    #[test]
+    #[ignore]
+    fn ignored() {}
+}
+
+fn main() {
+    tests::runner();
+}
@@ -5,8 +5,11 @@
 //! Similar to `defmt-test` but can output on any interface (typically blocking)
 //! and can be run interactively (select a test via serial interface).
 //!
+//! With the `std` feature the test module runs on the host: no panic handler
+//! is generated and panics are caught with `catch_unwind` instead.
+//!
 //! # Example
-//! ```no_run
+//! ```ignore
 //! /* Setup omitted */
 //! #[bern_test::tests]
 //! mod tests {
@@ -18,7 +21,9 @@
 //!     #[test_tear_down]
 //!     fn reset() {
 //!         // Runs after every test
-//!         // For autorun this must be soft reset
+//!         // For autorun without the `in-process` feature this must be a
+//!         // soft reset, after a panic the device is restarted anyway (see
+//!         // `bern_test::reset::restart()`)
 //!         cortex_m::peripheral::SCB::sys_reset();
 //!     }
 //!
@@ -34,6 +39,36 @@
 //!     }
 //!
 //!     #[test]
+//!     fn fallible_test(board: &mut Board) -> Result<(), Error> {
+//!         // An error fails the test without a panic
+//!         board.sensor.read()?;
+//!         Ok(())
+//!     }
+//!
+//!     #[test]
+//!     #[should_panic(expected = "index out of bounds")]
+//!     fn out_of_bounds() {
+//!         // Passes only if the panic message contains the expected string
+//!         let a = [0u8; 2];
+//!         let _ = a[core::hint::black_box(2)];
+//!     }
+//!
+//!     #[test]
+//!     #[timeout_ms = 100]
+//!     fn wait_for_interrupt() {
+//!         // Fails if it takes longer than 100ms (see `bern_test::watchdog`),
+//!         // a module default can be set with
+//!         // `#[bern_test::tests(timeout_ms = 1000)]`
+//!     }
+//!
+//!     #[test]
+//!     #[ignore]
+//!     fn slow_test() {
+//!         // Skipped in a run over all tests, can be selected in interactive
+//!         // mode
+//!     }
+//!
+//!     #[test]
 //!     fn test_with_hardware(board: &mut Board) {
 //!         // A test can use the argument given when the runner is started
 //!         // from main
@@ -47,14 +82,35 @@ extern crate proc_macro;
 use proc_macro::TokenStream;
 use proc_macro2::Ident;
 use quote::quote;
-use syn::{parse, spanned::Spanned, Item, ItemFn, ItemMod, FnArg, Pat};
+use syn::{parse, spanned::Spanned, Attribute, Item, ItemFn, ItemMod, FnArg, Pat};
+use syn::{AttributeArgs, Lit, Meta, NestedMeta, ReturnType};
 
 /// Test module proc macro.
 ///
 /// See [module level documentation](index.html).
 #[proc_macro_attribute]
-pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
+pub fn tests(args: TokenStream, input: TokenStream) -> TokenStream {
     let module: ItemMod = syn::parse(input).unwrap();
+    let args = syn::parse_macro_input!(args as AttributeArgs);
+
+    /* module level default timeout */
+    let mut default_timeout = None;
+    for arg in args.iter() {
+        match arg {
+            NestedMeta::Meta(meta) if meta.path().is_ident("timeout_ms") => {
+                default_timeout = match parse_timeout(meta) {
+                    Ok(timeout) => Some(timeout),
+                    Err(e) => return e.to_compile_error().into(),
+                };
+            },
+            _ => {
+                return parse::Error::new(
+                    arg.span(),
+                    "expected `timeout_ms = N`",
+                ).to_compile_error().into();
+            },
+        }
+    }
 
     let items = if let Some(content) = module.content {
         content.1
@@ -80,6 +136,8 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
             Item::Fn(func) => {
                 let mut test = false;
                 let mut should_panic = false;
+                let mut expected = None;
+                let mut timeout = default_timeout;
                 let mut ignored = false;
                 let mut test_set_up = false;
                 let mut test_tear_down = false;
@@ -91,6 +149,15 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
                         test = true;
                     } else if attr.path.is_ident("should_panic") {
                         should_panic = true;
+                        expected = match parse_expected(attr) {
+                            Ok(expected) => expected,
+                            Err(e) => return e.to_compile_error().into(),
+                        };
+                    } else if attr.path.is_ident("timeout_ms") {
+                        timeout = match attr.parse_meta().and_then(|meta| parse_timeout(&meta)) {
+                            Ok(timeout) => Some(timeout),
+                            Err(e) => return e.to_compile_error().into(),
+                        };
                     } else if attr.path.is_ident("ignore") {
                         ignored = true;
                     } else if attr.path.is_ident("test_set_up") {
@@ -116,7 +183,7 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
                             // self not supported
                         }
                     }
-                    if test_input_types.len() == 0 {
+                    if test_input_types.is_empty() {
                         test_input_types = types;
                         test_input_idents = idents;
                     } else {
@@ -124,11 +191,21 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
                     }
                 }
 
-                if test && !ignored {
+                if test && should_panic && func.sig.output != ReturnType::Default {
+                    return parse::Error::new(
+                        func.sig.output.span(),
+                        "functions using `#[should_panic]` must return `()`",
+                    ).to_compile_error().into();
+                }
+
+                if test {
                     tests.push(Test {
                         name,
                         func,
                         should_panic,
+                        expected,
+                        timeout,
+                        ignored,
                     });
                 } else if test_set_up {
                     test_set_up_code = func.block.stmts;
@@ -157,6 +234,10 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
     let module_name_string = format!("{}", module.ident);
     let test_blocks = tests.iter().map(|t| &t.func.block);
     let test_should_panic = tests.iter().map(|t| &t.should_panic);
+    let test_expected = tests.iter().map(|t| match &t.expected {
+        Some(expected) => quote! { Some(#expected) },
+        None => quote! { None },
+    });
     let test_sig = tests.iter().map(|t| &t.func.sig);
 
 
@@ -169,11 +250,19 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
     let test_input_call = quote! {
         #(#test_input_names,)*
     };
+    let watchdog_start = tests.iter().map(|t| match t.timeout {
+        Some(timeout) => quote! { bern_test::watchdog::start(#timeout); },
+        None => quote! {},
+    });
+    let watchdog_stop = tests.iter().map(|t| match t.timeout {
+        Some(_) => quote! { bern_test::watchdog::stop(); },
+        None => quote! {},
+    });
     let test_calls = tests.iter().map(|t| {
         let call = &t.name;
         match t.func.sig.inputs.len() {
-            0 => quote! { #call(); },
-            _ => quote! { #call(#test_input_call); },
+            0 => quote! { #call() },
+            _ => quote! { #call(#test_input_call) },
         }
     });
 
@@ -181,7 +270,64 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
     let i = (0..test_calls.len()).map(syn::Index::from);
     let k = i.clone(); // meh
     let name_copy = name_strings.clone();
-    let n_tests = tests.len() as u8;
+    let name_names = name_strings.clone();
+    let test_ignored = tests.iter().map(|t| t.ignored);
+    let ignored_copy = test_ignored.clone();
+    /* indices are `u16`, see `bern_test::MAX_TESTS` */
+    if tests.len() > u16::MAX as usize {
+        return parse::Error::new(
+            module.ident.span(),
+            format!("too many tests ({}), at most {} are supported per module", tests.len(), u16::MAX),
+        ).to_compile_error().into();
+    }
+    let n_tests = tests.len() as u16;
+    /* FNV-1a hash of the test names, detects a state left by another firmware */
+    let names_hash = tests.iter()
+        .flat_map(|t| format!("{}::{}\n", module_name_string, t.name).into_bytes())
+        .fold(0x811c_9dc5u32, |hash, b| (hash ^ b as u32).wrapping_mul(0x0100_0193));
+
+    /* on the host (`std`) a panic is caught instead of resetting the device */
+    let (run_call, panic_hook, run_all_loop, test_exit, panic_tear_down, panic_handler) = if cfg!(feature = "std") {
+        (
+            quote! { bern_test::run_catching(|| __run(test_index, #test_input_call)); },
+            quote! { bern_test::set_panic_hook(panicked); },
+            quote! {
+                while bern_test::run_all::is_active() {
+                    __runall(#test_input_call);
+                }
+            },
+            quote! { bern_test::report_exit(bern_test::last_test_succeeded()); },
+            quote! {},
+            quote! {},
+        )
+    } else {
+        (
+            quote! { __run(test_index, #test_input_call); },
+            quote! {},
+            quote! {
+                /* without `in-process` the test tear down resets the device */
+                while bern_test::run_all::is_active() {
+                    __runall(#test_input_call);
+                    if !bern_test::is_in_process_enabled() {
+                        break;
+                    }
+                }
+            },
+            quote! {},
+            quote! { __test_tear_down(); },
+            quote! {
+                use core::panic::PanicInfo;
+
+                #[panic_handler]
+                fn panic(info: &PanicInfo) -> ! {
+                    #module_name::panicked(info, &info.message());
+                    /* continue a run over all tests even if the tear down does not reset */
+                    bern_test::reset::restart();
+                }
+            },
+        )
+    };
+
     /* Create test module containing:
      * - a test runner
      * - the test function implementations
@@ -191,12 +337,24 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
             #(#imports)*
 
             use bern_test::{println, print, term_green, term_red, term_reset, term_gray};
-            use core::panic::PanicInfo;
+            use core::fmt::Display;
             use core::sync::atomic::{AtomicBool, Ordering};
 
             static SHOULD_PANIC: AtomicBool = AtomicBool::new(false);
+            const __NAMES: [&str; #n_tests as usize] = [#(#name_names,)*];
+            const __IGNORED: [bool; #n_tests as usize] = [#(#ignored_copy,)*];
+            const __EXPECTED: [Option<&str>; #n_tests as usize] = [#(#test_expected,)*];
+            /* outcome of every test in a run over all tests, kept across resets */
+            #[link_section = ".uninit"]
+            static mut __OUTCOMES: [u8; #n_tests as usize] = [0; #n_tests as usize];
 
             pub fn runner(#test_input_declaration) {
+                #panic_hook
+                bern_test::run_all::set_module(
+                    #n_tests,
+                    #names_hash,
+                    unsafe { &mut *core::ptr::addr_of_mut!(__OUTCOMES) },
+                );
                 if bern_test::is_autorun_enabled() && !bern_test::run_all::is_active() {
                     __print_header();
                     __runall_initiate();
@@ -204,96 +362,108 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
                     // provide user interface
                     __print_header();
                     __list_tests();
-                    let test_index = match bern_test::console::handle_user_input() {
-                        255 => {
+                    match bern_test::console::handle_user_input(#n_tests) {
+                        bern_test::console::Selection::RunAll => {
                             __runall_initiate();
                         },
-                        i => {
+                        bern_test::console::Selection::Test(test_index) => {
                             println!("");
                             __test_set_up();
-                            __run(i, #test_input_call);
+                            #run_call
                             __test_tear_down();
+                            #test_exit
                         },
-                    };
+                    }
                 }
 
-                if bern_test::run_all::is_active() {
-                    __runall(#test_input_call);
-                }
+                #run_all_loop
             }
 
             fn __print_header() {
-                println!(term_reset!());
-                println!("~~~~~~~~~~~~~~ Bern Test v{} ~~~~~~~~~~~~~~",
-                    bern_test::get_version(),
-                );
+                bern_test::report::header();
             }
 
             fn __list_tests() {
                 #(
-                    println!("[{}] {}::{}", #k, #module_name_string, #name_copy);
+                    bern_test::report::list_test(#k, #module_name_string, #name_copy, #test_ignored);
                 )*
-                println!("[255] run all tests");
-                println!("Select test [0..{}]:", #n_tests-1);
+                bern_test::report::list_footer(#n_tests);
             }
 
             fn __runall_initiate() {
                 bern_test::run_all::activate();
                 bern_test::run_all::set_next_test(0);
-                println!("\nrunning {} tests", #n_tests);
+                bern_test::report::run_started(#n_tests);
             }
 
             fn __runall(#test_input_declaration) {
-                let test_index = bern_test::run_all::get_next_test();
+                /* the device was reset during the previous test */
+                bern_test::report_interrupted(#module_name_string, &__NAMES);
+                let mut test_index = bern_test::run_all::get_next_test();
+                /* ignored tests are skipped within the same boot */
+                while test_index < #n_tests && __IGNORED[test_index as usize] {
+                    bern_test::test_ignored(
+                        test_index,
+                        #module_name_string,
+                        __NAMES[test_index as usize],
+                    );
+                    test_index += 1;
+                    bern_test::run_all::set_next_test(test_index);
+                }
                 if test_index < #n_tests {
                     bern_test::run_all::set_next_test(test_index + 1);
                     __test_set_up();
-                    __run(test_index, #test_input_call);
+                    #run_call
                     __test_tear_down();
                 } else {
-                    let successes = bern_test::run_all::get_success_count();
-                    let summary =  match successes {
-                        #n_tests => term_green!("ok"),
-                        _ => term_red!("FAILED"),
-                    };
-                    println!(
-                        "\ntest result: {}. {} passed; {} failed",
-                        summary,
-                        successes,
-                        #n_tests - successes,
-                    );
+                    bern_test::report::summary(#module_name_string, &__NAMES);
+                    let success = bern_test::run_all::succeeded();
                     bern_test::run_all::deactivate();
                     __tear_down();
+                    bern_test::report_exit(success);
                 }
             }
 
-            fn __run(index: u8, #test_input_declaration) {
+            fn __run(index: u16, #test_input_declaration) {
                 match index {
                 #(
                     #i => {
-                        print!("test {}::{} ... ", #module_name_string, #name_strings);
+                        bern_test::report::test_started(#i, #module_name_string, #name_strings);
+                        bern_test::run_all::test_started(#i);
                         /* setting boolean takes only one instruction */
                         SHOULD_PANIC.store(#test_should_panic, Ordering::SeqCst);
-                        #test_calls
-                        /* if we get here the test did not panic */
-                        if !#test_should_panic {
-                            bern_test::test_succeeded();
-                        } else {
-                            bern_test::test_failed(" └─ did not panic");
+                        #watchdog_start
+                        let result = #test_calls;
+                        #watchdog_stop
+                        /* an error is reported by `check()` */
+                        if bern_test::TestReturn::check(result) {
+                            /* if we get here the test did not panic */
+                            if !#test_should_panic {
+                                bern_test::test_succeeded();
+                            } else {
+                                bern_test::test_failed(&"did not panic");
+                            }
                         }
                     },
                 )*
-                    _ => (),
+                    _ => bern_test::test_not_found(index),
                 };
             }
 
-            pub fn panicked(info: &PanicInfo) {
+            // `info` is reported, `message` (without location) is matched
+            // against `#[should_panic(expected = "...")]`
+            pub fn panicked(info: &dyn Display, message: &dyn Display) {
+                bern_test::set_panicking(true);
+                bern_test::watchdog::stop();
                 if SHOULD_PANIC.load(Ordering::Relaxed) {
-                    bern_test::test_succeeded();
+                    match __EXPECTED[bern_test::report::current_test() as usize] {
+                        Some(expected) => bern_test::test_panicked_expected(message, expected),
+                        None => bern_test::test_succeeded(),
+                    }
                 } else {
                     bern_test::test_panicked(info);
                 }
-                __test_tear_down();
+                #panic_tear_down
             }
 
             // runs before every test
@@ -303,11 +473,14 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
 
             // runs after every test
             fn __test_tear_down() {
+                bern_test::flush();
                 #( #test_tear_down_code )*
+                bern_test::set_panicking(false);
             }
 
             // runs after all tests
             fn __tear_down() {
+                bern_test::flush();
                 #( #tear_down_code )*
             }
 
@@ -316,18 +489,9 @@ pub fn tests(_args: TokenStream, input: TokenStream) -> TokenStream {
             )*
         }
 
-        use core::panic::PanicInfo;
-        use core::sync::atomic::{self, Ordering};
-
-        #[panic_handler]
-        fn panic(info: &PanicInfo) -> ! {
-            #module_name::panicked(info);
-            loop {
-                atomic::compiler_fence(Ordering::SeqCst);
-            }
-        }
+        #panic_handler
     };
-    return TokenStream::from(tokens);
+    TokenStream::from(tokens)
 }
 
 
@@ -335,4 +499,38 @@ struct Test {
     name: Ident,
     func: ItemFn,
     should_panic: bool,
+    expected: Option<String>,
+    timeout: Option<u32>,
+    ignored: bool,
+}
+
+/// Parse `timeout_ms = N`.
+fn parse_timeout(meta: &Meta) -> Result<u32, parse::Error> {
+    match meta {
+        Meta::NameValue(nv) => match &nv.lit {
+            Lit::Int(n) => n.base10_parse(),
+            lit => Err(parse::Error::new(lit.span(), "expected timeout in ms")),
+        },
+        _ => Err(parse::Error::new(meta.span(), "expected `timeout_ms = N`")),
+    }
+}
+
+/// Parse `#[should_panic]` or `#[should_panic(expected = "...")]`.
+fn parse_expected(attr: &Attribute) -> Result<Option<String>, parse::Error> {
+    let error = || parse::Error::new(
+        attr.span(),
+        "expected `#[should_panic]` or `#[should_panic(expected = \"...\")]`",
+    );
+    match attr.parse_meta()? {
+        Meta::Path(_) => Ok(None),
+        Meta::List(list) => match list.nested.first() {
+            Some(NestedMeta::Meta(Meta::NameValue(nv)))
+                if list.nested.len() == 1 && nv.path.is_ident("expected") => match &nv.lit {
+                    Lit::Str(s) => Ok(Some(s.value())),
+                    _ => Err(error()),
+                },
+            _ => Err(error()),
+        },
+        Meta::NameValue(_) => Err(error()),
+    }
 }
\ No newline at end of file
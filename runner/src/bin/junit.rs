@@ -0,0 +1,50 @@
+//! `bern-test-junit` command line interface.
+//!
+//! ```text
+//! bern-test-junit [LOG] [-o OUTPUT]
+//! ```
+//!
+//! Converts a captured bern-test session log (stdin if no file is given) to
+//! JUnit XML (stdout if no output is given).
+use std::{env, fs, process};
+use std::io::{self, Read};
+use bern_test_runner::junit;
+use bern_test_runner::session::Report;
+
+fn main() {
+    let mut log_path = None;
+    let mut output_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output_path = args.next(),
+            a if a.starts_with('-') => {
+                eprintln!("error: unknown option {}", a);
+                process::exit(2);
+            },
+            _ => log_path = Some(arg),
+        }
+    }
+
+    let mut log = Vec::new();
+    let read = match &log_path {
+        Some(path) => fs::File::open(path).and_then(|mut f| f.read_to_end(&mut log)),
+        None => io::stdin().read_to_end(&mut log),
+    };
+    if let Err(e) = read {
+        eprintln!("error: could not read log: {}", e);
+        process::exit(2);
+    }
+
+    let report = Report::from_log(&String::from_utf8_lossy(&log));
+    let xml = junit::to_xml(&report);
+    match &output_path {
+        Some(path) => {
+            if let Err(e) = fs::write(path, xml) {
+                eprintln!("error: could not write {}: {}", path, e);
+                process::exit(2);
+            }
+        },
+        None => print!("{}", xml),
+    }
+}
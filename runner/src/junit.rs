@@ -0,0 +1,114 @@
+//! JUnit XML report.
+//!
+//! One `<testsuite>` per test module and one `<testcase>` per test. The
+//! failure message is taken from the panic block (`└─ stdout:`), ignored tests
+//! are marked as skipped.
+use std::fmt::Write;
+use crate::parser::{Outcome, TestResult};
+use crate::session::Report;
+
+/// Convert a report to JUnit XML.
+pub fn to_xml(report: &Report) -> String {
+    let count = |tests: &[&TestResult], f: fn(&Outcome) -> bool| {
+        tests.iter().filter(|t| f(&t.outcome)).count()
+    };
+    let is_failed: fn(&Outcome) -> bool = |o| matches!(o, Outcome::Failed(_));
+    let is_ignored: fn(&Outcome) -> bool = |o| *o == Outcome::Ignored;
+
+    /* group tests by module, keeping the order of appearance */
+    let mut modules: Vec<(&str, Vec<&TestResult>)> = vec![];
+    for test in &report.tests {
+        let (module, _) = split_name(&test.name);
+        match modules.iter_mut().find(|(m, _)| *m == module) {
+            Some((_, tests)) => tests.push(test),
+            None => modules.push((module, vec![test])),
+        }
+    }
+
+    let all = report.tests.iter().collect::<Vec<_>>();
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        xml,
+        "<testsuites name=\"bern-test\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">",
+        all.len(),
+        count(&all, is_failed),
+        count(&all, is_ignored),
+    ).unwrap();
+    for (module, tests) in modules {
+        writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">",
+            escape(module),
+            tests.len(),
+            count(&tests, is_failed),
+            count(&tests, is_ignored),
+        ).unwrap();
+        for test in tests {
+            let (_, name) = split_name(&test.name);
+            let open = format!(
+                "    <testcase classname=\"{}\" name=\"{}\"",
+                escape(module),
+                escape(name),
+            );
+            match &test.outcome {
+                Outcome::Passed => writeln!(xml, "{}/>", open).unwrap(),
+                Outcome::Ignored => {
+                    writeln!(xml, "{}>", open).unwrap();
+                    xml.push_str("      <skipped/>\n");
+                    xml.push_str("    </testcase>\n");
+                },
+                Outcome::Failed(message) => {
+                    let details = failure_details(message);
+                    let summary = details.lines()
+                        .find(|l| !l.trim().is_empty())
+                        .unwrap_or("FAILED");
+                    writeln!(xml, "{}>", open).unwrap();
+                    writeln!(
+                        xml,
+                        "      <failure message=\"{}\">{}</failure>",
+                        escape(summary.trim()),
+                        escape(&details),
+                    ).unwrap();
+                    xml.push_str("    </testcase>\n");
+                },
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Split `module::test` at the last path separator.
+fn split_name(name: &str) -> (&str, &str) {
+    name.rsplit_once("::").unwrap_or(("", name))
+}
+
+/// Remove the tree markers of the failure block (` └─ stdout:`), the
+/// indentation of the message (e.g. assertion diffs) is kept.
+fn failure_details(message: &str) -> String {
+    message.lines()
+        .filter(|l| l.trim() != "└─ stdout:")
+        .map(|l| l.strip_prefix(" └─ ").unwrap_or(l))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escape XML special characters and drop characters invalid in XML.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
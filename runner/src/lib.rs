@@ -0,0 +1,20 @@
+//! Host test runner for bern-test targets.
+//!
+//! Connects to a target over a serial port, selects all tests if the target
+//! runs in interactive mode and parses the results. The runner exits with a
+//! non-zero code if any test failed, so it can be used as cargo `runner`.
+//!
+//! A captured session log can be converted to JUnit XML with
+//! `bern-test-junit` (or `--junit PATH` when running).
+//!
+//! # Example
+//! `.cargo/config.toml`:
+//! ```toml
+//! [target.thumbv7em-none-eabihf]
+//! runner = "bern-test-runner --port /dev/ttyACM0 --flash 'probe-rs download --chip STM32F446RETx {}'"
+//! ```
+
+pub mod junit;
+pub mod parser;
+pub mod record;
+pub mod session;
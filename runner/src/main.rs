@@ -0,0 +1,113 @@
+//! `bern-test-runner` command line interface.
+//!
+//! ```text
+//! bern-test-runner [--port PATH] [--baud RATE] [--timeout SECONDS]
+//!                  [--flash COMMAND] [--junit PATH] [ELF]
+//! ```
+//!
+//! The port can also be set with the `BERN_TEST_PORT` environment variable.
+//! `{}` in the flash command is replaced with the ELF path, the path is passed
+//! to the shell as an argument and needs no quoting. With `--junit` a JUnit
+//! XML report is written after the run.
+use std::{env, fs};
+use std::process::{self, Command};
+use std::time::Duration;
+use bern_test_runner::junit;
+use bern_test_runner::session::{self, Config};
+
+struct Args {
+    port: Option<String>,
+    baud: u32,
+    timeout: u64,
+    flash: Option<String>,
+    junit: Option<String>,
+    elf: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        port: env::var("BERN_TEST_PORT").ok(),
+        baud: 115_200,
+        timeout: 60,
+        flash: None,
+        junit: None,
+        elf: None,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| iter.next().ok_or(format!("missing value for {}", name));
+        match arg.as_str() {
+            "--port" => args.port = Some(value("--port")?),
+            "--baud" => args.baud = value("--baud")?.parse().map_err(|_| "invalid baud rate")?,
+            "--timeout" => args.timeout = value("--timeout")?.parse().map_err(|_| "invalid timeout")?,
+            "--flash" => args.flash = Some(value("--flash")?),
+            "--junit" => args.junit = Some(value("--junit")?),
+            a if a.starts_with("--") => return Err(format!("unknown option {}", a)),
+            _ => args.elf = Some(arg),
+        }
+    }
+    Ok(args)
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        },
+    };
+    let port_name = match &args.port {
+        Some(port) => port,
+        None => {
+            eprintln!("error: no port given (--port or BERN_TEST_PORT)");
+            process::exit(2);
+        },
+    };
+
+    /* open the port before flashing, so the header is not missed */
+    let mut port = match serialport::new(port_name, args.baud)
+        .timeout(Duration::from_millis(100))
+        .open()
+    {
+        Ok(port) => port,
+        Err(e) => {
+            eprintln!("error: could not open {}: {}", port_name, e);
+            process::exit(2);
+        },
+    };
+
+    if let Some(flash) = &args.flash {
+        /* the ELF path is the first positional parameter of the script */
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(flash.replace("{}", "\"$1\""))
+            .arg("sh")
+            .arg(args.elf.as_deref().unwrap_or(""))
+            .status();
+        if !status.is_ok_and(|s| s.success()) {
+            eprintln!("error: flash command failed: {}", flash);
+            process::exit(2);
+        }
+    }
+
+    let config = Config {
+        timeout: Duration::from_secs(args.timeout),
+        ..Config::default()
+    };
+    match session::run(&mut port, &config, |line| println!("{}", line.trim_end())) {
+        Ok(report) => {
+            if let Some(path) = &args.junit {
+                if let Err(e) = fs::write(path, junit::to_xml(&report)) {
+                    eprintln!("error: could not write {}: {}", path, e);
+                    process::exit(2);
+                }
+            }
+            process::exit(if report.success() { 0 } else { 1 });
+        },
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        },
+    }
+}
@@ -0,0 +1,303 @@
+//! Parser for the bern-test output of a target.
+//!
+//! Lines are fed one by one, the parser emits an [`Event`] whenever a part of
+//! the session is complete. Terminal colors are removed.
+//!
+//! Both the text output and the records of the machine readable protocol
+//! (see [`record`](crate::record)) are understood.
+use crate::record::{self, Record};
+
+/// Result of a single test.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Test passed.
+    Passed,
+    /// Test failed, with failure message (e.g. panic info).
+    Failed(String),
+    /// Test ignored.
+    Ignored,
+}
+
+/// Parsed test result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResult {
+    /// Full test name (`module::test`).
+    pub name: String,
+    /// Test outcome.
+    pub outcome: Outcome,
+}
+
+/// Summary at the end of a run over all tests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    /// Number of passed tests.
+    pub passed: usize,
+    /// Number of failed tests.
+    pub failed: usize,
+    /// Number of ignored tests.
+    pub ignored: usize,
+}
+
+/// Parser events.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// `~~~ Bern Test vX ~~~` header.
+    Header { version: String },
+    /// Entry of the test list in interactive mode.
+    ListEntry { index: usize, name: String, ignored: bool },
+    /// Target waits for a test selection.
+    Prompt,
+    /// Run over all tests started.
+    Running { count: usize },
+    /// A test finished.
+    Test(TestResult),
+    /// Final `test result:` line.
+    Summary(Summary),
+    /// Record with invalid checksum or format.
+    Corrupted(String),
+}
+
+/// Line based parser.
+#[derive(Debug, Default)]
+pub struct Parser {
+    /// Test started, result not yet printed.
+    started: Option<String>,
+    /// Test failed, collecting the failure message.
+    failed: Option<(String, Vec<String>)>,
+    /// Incomplete record (message spanning multiple lines).
+    record: Option<String>,
+    /// Test names from `START` records.
+    names: Vec<(usize, String)>,
+    /// In the `failures:` list before the summary.
+    failures: bool,
+}
+
+impl Parser {
+    /// Create a new parser.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse one line (without `\n`), returns all completed events.
+    pub fn feed_line(&mut self, line: &str) -> Vec<Event> {
+        /* keep `\r` of multi-line messages, it is part of the checksum */
+        let raw = line.trim_end_matches('\n');
+        if let Some(pending) = &mut self.record {
+            pending.push('\n');
+            pending.push_str(raw);
+            return self.complete_record();
+        }
+        if let Some(start) = raw.find(record::PREFIX) {
+            self.record = Some(raw[start..].to_string());
+            return self.complete_record();
+        }
+
+        let line = strip_ansi(line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        let mut events = vec![];
+
+        if let Some(version) = parse_header(line) {
+            events.extend(self.finish());
+            self.failures = false;
+            events.push(Event::Header { version });
+        } else if let Some(summary) = parse_summary(line) {
+            events.extend(self.finish());
+            self.failures = false;
+            events.push(Event::Summary(summary));
+        } else if line == "failures:" {
+            events.extend(self.finish());
+            self.failures = true;
+        } else if self.failures {
+            /* names of the failed tests, already reported */
+        } else if let Some(rest) = line.strip_prefix("test ") {
+            events.extend(self.finish());
+            if let Some((name, result)) = rest.split_once(" ...") {
+                self.started = Some(name.trim().to_string());
+                events.extend(self.parse_result(result.trim()));
+            }
+        } else if let Some((_, messages)) = &mut self.failed {
+            messages.push(line.to_string());
+        } else if self.started.is_some() {
+            /* test output before the result */
+            events.extend(self.parse_result(line.trim()));
+        } else if let Some(count) = parse_running(line) {
+            events.push(Event::Running { count });
+        } else if line.starts_with("Select test") {
+            events.push(Event::Prompt);
+        } else if let Some((index, name, ignored)) = parse_list_entry(line) {
+            events.push(Event::ListEntry { index, name, ignored });
+        }
+        events
+    }
+
+    /// Complete any pending test, e.g. at the end of the input.
+    pub fn finish(&mut self) -> Option<Event> {
+        self.started = None;
+        if let Some(pending) = self.record.take() {
+            return Some(Event::Corrupted(pending));
+        }
+        self.failed.take().map(|(name, mut messages)| {
+            while messages.last().is_some_and(|m| m.trim().is_empty()) {
+                messages.pop();
+            }
+            Event::Test(TestResult {
+                name,
+                outcome: Outcome::Failed(messages.join("\n")),
+            })
+        })
+    }
+
+    /// Decode the pending record if it is complete.
+    fn complete_record(&mut self) -> Vec<Event> {
+        let pending = match &self.record {
+            Some(pending) => pending,
+            None => return vec![],
+        };
+        match record::body_len(pending) {
+            /* checksum not yet received */
+            Some(len) if pending.len() < len + 5 => return vec![],
+            None if pending.starts_with("#BT:FAIL ") && !pending.contains('*') => return vec![],
+            _ => (),
+        }
+        let pending = self.record.take().unwrap();
+        let decoded = match record::decode(&pending) {
+            Some(decoded) => decoded,
+            None => return vec![Event::Corrupted(pending)],
+        };
+        let name = |names: &[(usize, String)], index| {
+            names.iter()
+                .rev()
+                .find(|(i, _)| *i == index)
+                .map_or(format!("#{}", index), |(_, n)| n.clone())
+        };
+        let event = match decoded {
+            Record::Hello { protocol, version } if protocol == record::PROTOCOL_VERSION => {
+                self.names.clear();
+                Event::Header { version }
+            },
+            Record::Hello { .. } => Event::Corrupted(pending),
+            Record::List { index, name, ignored } => Event::ListEntry { index, name, ignored },
+            Record::Select { .. } => Event::Prompt,
+            Record::Run { count } => Event::Running { count },
+            Record::Start { index, name } => {
+                self.names.push((index, name));
+                return vec![];
+            },
+            Record::Pass { index } => Event::Test(TestResult {
+                name: name(&self.names, index),
+                outcome: Outcome::Passed,
+            }),
+            Record::Fail { index, message } => Event::Test(TestResult {
+                name: name(&self.names, index),
+                outcome: Outcome::Failed(message.replace('\r', "")),
+            }),
+            Record::Ignore { name, .. } => Event::Test(TestResult {
+                name,
+                outcome: Outcome::Ignored,
+            }),
+            Record::Summary { passed, failed, ignored } => Event::Summary(Summary {
+                passed,
+                failed,
+                ignored,
+            }),
+            Record::Reject { .. } | Record::Stats { .. } => return vec![],
+        };
+        vec![event]
+    }
+
+    /// Parse the result of the started test, anything else is test output
+    /// (e.g. `sensor init ok`) and the test stays started.
+    fn parse_result(&mut self, result: &str) -> Option<Event> {
+        if result == "FAILED" {
+            let name = self.started.take()?;
+            self.failed = Some((name, vec![]));
+            None
+        } else if let Some(reason) = result.strip_prefix("FAILED (") {
+            /* e.g. `FAILED (timeout)` */
+            let name = self.started.take()?;
+            self.failed = Some((name, vec![reason.trim_end_matches(')').to_string()]));
+            None
+        } else if result == "ignored" {
+            let name = self.started.take()?;
+            Some(Event::Test(TestResult {
+                name,
+                outcome: Outcome::Ignored,
+            }))
+        } else if result == "ok" {
+            let name = self.started.take()?;
+            Some(Event::Test(TestResult {
+                name,
+                outcome: Outcome::Passed,
+            }))
+        } else {
+            None
+        }
+    }
+}
+
+/// Remove ANSI escape sequences (terminal colors).
+pub fn strip_ansi(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1B' {
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+fn parse_header(line: &str) -> Option<String> {
+    let rest = line.trim_matches(|c| c == '~' || c == ' ');
+    let version = rest.strip_prefix("Bern Test v")?;
+    Some(version.trim().to_string())
+}
+
+fn parse_summary(line: &str) -> Option<Summary> {
+    let rest = line.strip_prefix("test result: ")?;
+    let (_, counts) = rest.split_once(". ")?;
+    let mut passed = None;
+    let mut failed = None;
+    let mut ignored = 0;
+    for part in counts.split(';') {
+        let mut words = part.split_whitespace();
+        let count = words.next()?.parse().ok()?;
+        match words.next()? {
+            "passed" => passed = Some(count),
+            "failed" => failed = Some(count),
+            "ignored" => ignored = count,
+            _ => (),
+        }
+    }
+    Some(Summary {
+        passed: passed?,
+        failed: failed?,
+        ignored,
+    })
+}
+
+fn parse_running(line: &str) -> Option<usize> {
+    let rest = line.strip_prefix("running ")?;
+    rest.strip_suffix(" tests")?.parse().ok()
+}
+
+fn parse_list_entry(line: &str) -> Option<(usize, String, bool)> {
+    let rest = line.strip_prefix('[')?;
+    let (index, name) = rest.split_once("] ")?;
+    if !name.contains("::") {
+        return None;
+    }
+    let (name, ignored) = match name.strip_suffix(" (ignored)") {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    Some((index.parse().ok()?, name.to_string(), ignored))
+}
@@ -0,0 +1,119 @@
+//! Records of the machine readable line protocol (`machine` feature of
+//! bern-test).
+//!
+//! ```text
+//! #BT:<TYPE> <fields>*<CRC>\r\n
+//! ```
+//! `CRC` is the CRC-16/CCITT-FALSE of all bytes from `#` up to `*`.
+
+/// Record prefix.
+pub const PREFIX: &str = "#BT:";
+/// Supported protocol version.
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Decoded record.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    Hello { protocol: u8, version: String },
+    List { index: usize, name: String, ignored: bool },
+    Select { count: usize },
+    Reject { reason: String },
+    Run { count: usize },
+    Start { index: usize, name: String },
+    Pass { index: usize },
+    Fail { index: usize, message: String },
+    Ignore { index: usize, name: String },
+    Summary { passed: usize, failed: usize, ignored: usize },
+    Stats { nested: u32, tx_dropped: u32, rx_dropped: u32 },
+}
+
+/// Update a CRC-16/CCITT-FALSE (initial value `0xFFFF`).
+pub fn crc16(mut crc: u16, bytes: &[u8]) -> u16 {
+    for b in bytes {
+        crc ^= (*b as u16) << 8;
+        for _ in 0..8 {
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x1021,
+            };
+        }
+    }
+    crc
+}
+
+/// Number of bytes of a record (starting at the prefix) up to the checksum,
+/// if enough of the record is available to tell.
+///
+/// Records other than `FAIL` are complete at the first `*`, `FAIL` records
+/// contain a length prefixed message.
+pub fn body_len(record: &str) -> Option<usize> {
+    if let Some(rest) = record.strip_prefix("#BT:FAIL ") {
+        let mut fields = rest.splitn(3, ' ');
+        let index = fields.next()?;
+        let len = fields.next()?;
+        fields.next()?;
+        let message_len: usize = len.parse().ok()?;
+        Some("#BT:FAIL ".len() + index.len() + 1 + len.len() + 1 + message_len)
+    } else {
+        record.find('*')
+    }
+}
+
+/// Verify the checksum and decode a complete record, `None` if it is
+/// corrupted.
+pub fn decode(record: &str) -> Option<Record> {
+    let body = record.get(..body_len(record)?)?;
+    let crc = record.get(body.len()..)?.strip_prefix('*')?;
+    let crc = u16::from_str_radix(crc.get(..4)?, 16).ok()?;
+    if crc16(0xFFFF, body.as_bytes()) != crc {
+        return None;
+    }
+
+    let body = body.strip_prefix(PREFIX)?;
+    let (kind, fields) = body.split_once(' ').unwrap_or((body, ""));
+    let mut words = fields.splitn(3, ' ');
+    let mut next = || words.next();
+    let record = match kind {
+        "HELLO" => Record::Hello {
+            protocol: next()?.parse().ok()?,
+            version: next()?.to_string(),
+        },
+        "LIST" => Record::List {
+            index: next()?.parse().ok()?,
+            name: next()?.to_string(),
+            ignored: next() == Some("ignored"),
+        },
+        "SELECT" => Record::Select { count: next()?.parse().ok()? },
+        "REJECT" => Record::Reject { reason: fields.to_string() },
+        "RUN" => Record::Run { count: next()?.parse().ok()? },
+        "START" => Record::Start {
+            index: next()?.parse().ok()?,
+            name: fields.split_once(' ')?.1.to_string(),
+        },
+        "PASS" => Record::Pass { index: next()?.parse().ok()? },
+        "FAIL" => {
+            let index = next()?.parse().ok()?;
+            next()?;
+            Record::Fail {
+                index,
+                message: next().unwrap_or("").to_string(),
+            }
+        },
+        "IGNORE" => Record::Ignore {
+            index: next()?.parse().ok()?,
+            name: fields.split_once(' ')?.1.to_string(),
+        },
+        "SUMMARY" => Record::Summary {
+            passed: next()?.parse().ok()?,
+            failed: next()?.parse().ok()?,
+            ignored: next().map_or(Some(0), |i| i.parse().ok())?,
+        },
+        "STATS" => Record::Stats {
+            nested: next()?.parse().ok()?,
+            tx_dropped: next()?.parse().ok()?,
+            rx_dropped: next()?.parse().ok()?,
+        },
+        _ => return None,
+    };
+    Some(record)
+}
@@ -0,0 +1,156 @@
+//! Drive a test session on a target.
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+use crate::parser::{Event, Outcome, Parser, Summary, TestResult};
+
+/// Session configuration.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Abort if the target is silent for this long.
+    pub timeout: Duration,
+    /// Command sent when the target asks for a test selection.
+    pub command: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            timeout: Duration::from_secs(60),
+            command: "a".to_string(),
+        }
+    }
+}
+
+/// Session errors.
+#[derive(Debug)]
+pub enum Error {
+    /// Error from the port.
+    Io(io::Error),
+    /// Target did not send anything within the timeout.
+    Timeout,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "port error: {}", e),
+            Error::Timeout => write!(f, "timeout waiting for target"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Result of a session.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// bern-test version of the target.
+    pub version: Option<String>,
+    /// Results of all tests in order.
+    pub tests: Vec<TestResult>,
+    /// Final summary, if the run completed.
+    pub summary: Option<Summary>,
+    /// Number of corrupted records.
+    pub corrupted: usize,
+}
+
+impl Report {
+    /// Parse a captured session log.
+    pub fn from_log(log: &str) -> Self {
+        let mut parser = Parser::new();
+        let mut report = Report::default();
+        let events = log.split('\n')
+            .flat_map(|line| parser.feed_line(line))
+            .collect::<Vec<_>>();
+        for event in events.into_iter().chain(parser.finish()) {
+            match event {
+                Event::Header { version } => report.version = Some(version),
+                Event::Test(result) => report.tests.push(result),
+                Event::Summary(summary) => report.summary = Some(summary),
+                Event::Corrupted(_) => report.corrupted += 1,
+                _ => (),
+            }
+        }
+        report
+    }
+
+    /// Whether the run completed, no test failed and no record was corrupted.
+    pub fn success(&self) -> bool {
+        self.corrupted == 0 &&
+            self.summary.is_some_and(|s| s.failed == 0) &&
+            !self.tests.iter().any(|t| matches!(t.outcome, Outcome::Failed(_)))
+    }
+}
+
+/// Run a session: wait for the header, select all tests if the target asks
+/// and collect results until the summary.
+///
+/// Every line received after the header is passed to `echo`.
+pub fn run<P, E>(port: &mut P, config: &Config, mut echo: E) -> Result<Report, Error>
+    where P: Read + Write,
+          E: FnMut(&str),
+{
+    let mut parser = Parser::new();
+    let mut report = Report::default();
+    let mut line = Vec::new();
+    let mut buffer = [0u8; 256];
+    let mut last_rx = Instant::now();
+
+    loop {
+        let n = match port.read(&mut buffer) {
+            Ok(0) => return Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::TimedOut ||
+                e.kind() == io::ErrorKind::WouldBlock ||
+                e.kind() == io::ErrorKind::Interrupted => 0,
+            Err(e) => return Err(e.into()),
+        };
+        if n == 0 {
+            if last_rx.elapsed() > config.timeout {
+                return Err(Error::Timeout);
+            }
+            continue;
+        }
+        last_rx = Instant::now();
+
+        for b in &buffer[..n] {
+            if *b != b'\n' {
+                line.push(*b);
+                continue;
+            }
+            let text = String::from_utf8_lossy(&line).into_owned();
+            line.clear();
+            let header_seen = report.version.is_some();
+            for event in parser.feed_line(&text) {
+                match event {
+                    Event::Header { version } => {
+                        report.version = Some(version);
+                    },
+                    _ if !header_seen => (),
+                    Event::Prompt => {
+                        port.write_all(format!("{}\n", config.command).as_bytes())?;
+                        port.flush()?;
+                    },
+                    Event::Test(result) => report.tests.push(result),
+                    Event::Corrupted(_) => report.corrupted += 1,
+                    Event::Summary(summary) => {
+                        echo(&text);
+                        report.summary = Some(summary);
+                        return Ok(report);
+                    },
+                    _ => (),
+                }
+            }
+            if report.version.is_some() {
+                echo(&text);
+            }
+        }
+    }
+}
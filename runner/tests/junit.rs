@@ -0,0 +1,74 @@
+//! JUnit XML conversion of a captured session log.
+use bern_test_runner::junit;
+use bern_test_runner::session::Report;
+
+const LOG: &str = "\
+boot noise\r
+\x1B[m\r
+~~~~~~~~~~~~~~ Bern Test v0.1.0 ~~~~~~~~~~~~~~\r
+\r
+running 4 tests\r
+test tests::first ... \x1B[32mok\x1B[m\r
+test tests::assert ... \x1B[31mFAILED\x1B[m\r
+ └─ stdout:\r
+panicked at src/main.rs:16:18:\r
+assertion `left == right` failed\r
+  left: 1 & <2>\r
+ right: 2\r
+test tests::skipped ... ignored\r
+test other::no_panic ... \x1B[31mFAILED\x1B[m\r
+ └─ did not panic\r
+test other::hangs ... \x1B[31mFAILED (timeout)\x1B[m\r
+test other::fault ... \x1B[31mFAILED\x1B[m\r
+ └─ stdout:\r
+panicked at src/other.rs:3:5:\r
+\r
+failures:\r
+    tests::assert\r
+    other::no_panic\r
+    other::hangs (timeout)\r
+    other::fault\r
+\r
+test result: \x1B[31mFAILED\x1B[m. 1 passed; 4 failed; 1 ignored\r
+";
+
+#[test]
+fn log_is_parsed() {
+    let report = Report::from_log(LOG);
+    assert_eq!(report.version.as_deref(), Some("0.1.0"));
+    assert_eq!(report.tests.len(), 6);
+    assert!(!report.success());
+}
+
+#[test]
+fn xml_has_testcases() {
+    let xml = junit::to_xml(&Report::from_log(LOG));
+    assert!(xml.contains("<testsuites name=\"bern-test\" tests=\"6\" failures=\"4\" skipped=\"1\">"));
+    assert!(xml.contains("<testsuite name=\"tests\" tests=\"3\" failures=\"1\" skipped=\"1\">"));
+    assert!(xml.contains("<testcase classname=\"tests\" name=\"first\"/>"));
+    assert!(xml.contains("<failure message=\"panicked at src/main.rs:16:18:\">"));
+    assert!(xml.contains("failed\n  left: 1 &amp; &lt;2&gt;\n right: 2</failure>"));
+    assert!(xml.contains("<failure message=\"did not panic\">did not panic</failure>"));
+    assert!(xml.contains("<failure message=\"timeout\">timeout</failure>"));
+    assert!(xml.contains(">panicked at src/other.rs:3:5:</failure>"));
+    assert!(xml.contains("<testcase classname=\"tests\" name=\"skipped\">\n      <skipped/>"));
+}
+
+#[test]
+fn output_before_failure() {
+    /* test output on the line of the test name must not be taken as result */
+    let log = "\
+running 1 tests\r
+test tests::init ... sensor init ok\r
+\x1B[31mFAILED\x1B[m\r
+ └─ stdout:\r
+panicked at src/main.rs:9:5:\r
+sensor not ready\r
+\r
+test result: \x1B[31mFAILED\x1B[m. 0 passed; 1 failed; 0 ignored\r
+";
+    let xml = junit::to_xml(&Report::from_log(log));
+    assert!(xml.contains("<testsuites name=\"bern-test\" tests=\"1\" failures=\"1\" skipped=\"0\">"), "{}", xml);
+    assert!(xml.contains("<failure message=\"panicked at src/main.rs:9:5:\">"));
+    assert!(xml.contains("sensor not ready</failure>"));
+}
@@ -0,0 +1,45 @@
+//! Round trip of the machine readable line protocol: the records printed by
+//! the `host` example of bern-test (`std` and `machine` features) are parsed
+//! by the runner.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use bern_test_runner::parser::Outcome;
+use bern_test_runner::session::Report;
+
+fn run_host_example() -> String {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let target_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("machine");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--quiet", "--example", "host", "--no-default-features"])
+        .args(["--features", "std,autorun,machine"])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .current_dir(crate_dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let output = Command::new(target_dir.join("debug/examples/host")).output().unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn target_records_are_parsed() {
+    let log = run_host_example();
+    let report = Report::from_log(&log);
+    assert_eq!(report.corrupted, 0, "{}", log);
+    /* protocol version of the target is supported */
+    assert!(report.version.is_some());
+    assert_eq!(report.tests.len(), 7);
+    assert_eq!(report.tests[0].name, "tests::passes");
+    assert_eq!(report.tests[0].outcome, Outcome::Passed);
+    /* multi-line message with `\r\n` line endings */
+    match &report.tests[1].outcome {
+        Outcome::Failed(message) => {
+            assert!(message.contains("  left: 4\n right: 5"), "{}", message);
+        },
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+    assert_eq!(report.tests[6].outcome, Outcome::Ignored);
+    assert_eq!(report.summary.map(|s| (s.passed, s.failed, s.ignored)), Some((3, 3, 1)));
+    assert!(!report.success());
+}
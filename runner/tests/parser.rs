@@ -0,0 +1,35 @@
+//! Parsing of test output printed between the test name and the result.
+use bern_test_runner::parser::Outcome;
+use bern_test_runner::session::Report;
+
+/* the target prints `test ... ` without newline, test output follows */
+const LOG: &str = "\
+running 3 tests\r
+test tests::init ... sensor init ok\r
+\x1B[31mFAILED\x1B[m\r
+ └─ stdout:\r
+panicked at src/main.rs:9:5:\r
+sensor not ready\r
+test tests::lookup ... lookup\r
+\x1B[32mok\x1B[m\r
+test tests::silent ... \x1B[32mok\x1B[m\r
+\r
+failures:\r
+    tests::init\r
+\r
+test result: \x1B[31mFAILED\x1B[m. 2 passed; 1 failed; 0 ignored\r
+";
+
+#[test]
+fn output_before_result() {
+    let report = Report::from_log(LOG);
+    assert_eq!(report.tests.len(), 3);
+    assert_eq!(report.tests[0].name, "tests::init");
+    match &report.tests[0].outcome {
+        Outcome::Failed(message) => assert!(message.ends_with("sensor not ready"), "{}", message),
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+    assert_eq!(report.tests[1].outcome, Outcome::Passed);
+    assert_eq!(report.tests[2].outcome, Outcome::Passed);
+    assert!(!report.success());
+}
@@ -0,0 +1,88 @@
+//! End-to-end tests of the runner binary against a simulated target on a
+//! pseudo terminal.
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::process::{Command, Output};
+use std::thread;
+use std::time::Duration;
+use serialport::{SerialPort, TTYPort};
+
+const HEADER: &str = "\x1B[m\r\n~~~~~~~~~~~~~~ Bern Test v0.1.0 ~~~~~~~~~~~~~~\r\n";
+
+/// Run the runner binary against a target simulated by `target`.
+fn run_against<F>(args: &[&str], target: F) -> Output
+    where F: FnOnce(&mut TTYPort) + Send + 'static
+{
+    let (mut master, slave) = TTYPort::pair().unwrap();
+    master.set_timeout(Duration::from_secs(5)).unwrap();
+    let port = slave.name().unwrap();
+
+    let target = thread::spawn(move || {
+        /* give the runner time to open the port */
+        thread::sleep(Duration::from_millis(200));
+        target(&mut master);
+        master
+    });
+    let output = Command::new(env!("CARGO_BIN_EXE_bern-test-runner"))
+        .args(["--port", &port])
+        .args(args)
+        .output()
+        .unwrap();
+    target.join().unwrap();
+    drop(slave);
+    output
+}
+
+fn read_command(port: &mut TTYPort) -> String {
+    let mut command = vec![];
+    let mut byte = [0u8; 1];
+    while port.read(&mut byte).unwrap() == 1 && byte[0] != b'\n' {
+        command.push(byte[0]);
+    }
+    String::from_utf8(command).unwrap()
+}
+
+#[test]
+fn interactive_run_passes() {
+    let output = run_against(&[], |target| {
+        target.write_all(HEADER.as_bytes()).unwrap();
+        target.write_all(b"[0] tests::first\r\n[1] tests::second\r\n[a] run all tests\r\nSelect test [0..1]:\r\n").unwrap();
+        assert_eq!(read_command(target), "a");
+        target.write_all(b"\r\nrunning 2 tests\r\n").unwrap();
+        target.write_all(b"test tests::first ... \x1B[32mok\x1B[m\r\n").unwrap();
+        target.write_all(b"test tests::second ... \x1B[32mok\x1B[m\r\n").unwrap();
+        target.write_all(b"\r\ntest result: \x1B[32mok\x1B[m. 2 passed; 0 failed\r\n").unwrap();
+    });
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("test tests::second ... "));
+    assert!(stdout.contains("test result: "));
+}
+
+#[test]
+fn autorun_failure_exits_non_zero() {
+    let output = run_against(&[], |target| {
+        /* noise before the header is ignored */
+        target.write_all(b"\x00\xffboot\r\n").unwrap();
+        target.write_all(HEADER.as_bytes()).unwrap();
+        target.write_all(b"\r\nrunning 2 tests\r\n").unwrap();
+        target.write_all(b"test tests::first ... \x1B[31mFAILED\x1B[m\r\n").unwrap();
+        target.write_all(b" \xe2\x94\x94\xe2\x94\x80 stdout:\r\npanicked at 'assertion failed'\r\n").unwrap();
+        target.write_all(b"test tests::second ... \x1B[32mok\x1B[m\r\n").unwrap();
+        target.write_all(b"\r\ntest result: \x1B[31mFAILED\x1B[m. 1 passed; 1 failed\r\n").unwrap();
+    });
+
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn silent_target_times_out() {
+    let output = run_against(&["--timeout", "1"], |target| {
+        target.write_all(HEADER.as_bytes()).unwrap();
+        thread::sleep(Duration::from_millis(1500));
+    });
+
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,47 @@
+//! Parsing of a session log in the machine readable line protocol.
+use bern_test_runner::parser::Outcome;
+use bern_test_runner::record::crc16;
+use bern_test_runner::session::Report;
+
+fn record(body: &str) -> String {
+    format!("{}*{:04X}\r\n", body, crc16(0xFFFF, body.as_bytes()))
+}
+
+#[test]
+fn records_are_parsed() {
+    let message = "panicked at src/main.rs:16:18:\r\nassertion failed";
+    let mut log = String::from("boot noise\r\n");
+    log += &record("#BT:HELLO 1 0.1.0");
+    log += &record("#BT:RUN 3");
+    log += &record("#BT:START 0 tests::first");
+    log += "output of the test\r\n";
+    log += &record("#BT:PASS 0");
+    log += &record("#BT:START 1 tests::second");
+    log += &record(&format!("#BT:FAIL 1 {} {}", message.len(), message));
+    log += &record("#BT:IGNORE 2 tests::third");
+    log += &record("#BT:SUMMARY 1 1 1");
+    log += &record("#BT:STATS 0 0 0");
+
+    let report = Report::from_log(&log);
+    assert_eq!(report.version.as_deref(), Some("0.1.0"));
+    assert_eq!(report.tests.len(), 3);
+    assert_eq!(report.tests[0].name, "tests::first");
+    assert_eq!(report.tests[1].outcome, Outcome::Failed(message.replace('\r', "")));
+    assert_eq!(report.tests[2].outcome, Outcome::Ignored);
+    assert_eq!(report.summary.map(|s| s.ignored), Some(1));
+    assert_eq!(report.corrupted, 0);
+    assert!(!report.success());
+}
+
+#[test]
+fn corrupted_record_fails_run() {
+    let mut log = record("#BT:HELLO 1 0.1.0");
+    log += &record("#BT:RUN 1");
+    log += &record("#BT:START 0 tests::first");
+    log += &record("#BT:PASS 0").replace("PASS", "PA5S");
+    log += &record("#BT:SUMMARY 1 0");
+
+    let report = Report::from_log(&log);
+    assert_eq!(report.corrupted, 1);
+    assert!(!report.success());
+}
@@ -1,38 +1,50 @@
-use nb::Error::Other;
-use crate::serial::{self, Serial};
+use crate::transport::{self, Error};
 use crate::println;
 
-pub fn handle_user_input() -> u8 {
+/// Command to run all tests in interactive mode.
+pub const RUN_ALL_COMMAND: &str = "a";
+
+/// Test selection in interactive mode.
+pub enum Selection {
+    /// Run a single test.
+    Test(u16),
+    /// Run all tests.
+    RunAll,
+}
+
+/// Read a test selection, indices must be below `n_tests`.
+pub fn handle_user_input(n_tests: u16) -> Selection {
     loop {
-        let mut command: &str = "";
         let mut rx_buffer = [0u8; 128];
 
-        #[cfg(feature = "serial")]
-            {
-                let ser = unsafe { Serial::steal() };
-                match ser.readln(&mut rx_buffer) {
-                    Ok(len) => {
-                        command = match core::str::from_utf8(&rx_buffer[0..len]) {
-                            Ok(c) => c,
-                            Err(_) => continue,
-                        };
-                    },
-                    Err(e) => match e {
-                        Other(serial::Error::BufferOverrun) => println!("Error: Serial RX buffer overflow"),
-                        Other(serial::Error::NoDownlink) => println!("Error: No serial downlink provided"),
-                        _ => println!("Error: Unknown serial error"),
-                    }
-                };
-            }
-
-        let test_index = match command.parse::<u8>() {
-            Ok(i) => i,
-            Err(_) => {
-                println!("Error: Could not parse test index");
-                continue;
+        let command = match transport::readln(&mut rx_buffer) {
+            Ok(len) => match core::str::from_utf8(&rx_buffer[0..len]) {
+                Ok(c) => c,
+                Err(_) => continue,
+            },
+            Err(e) => {
+                match e {
+                    Error::BufferOverrun => println!("Error: RX buffer overflow"),
+                    Error::NoDownlink => println!("Error: No downlink provided"),
+                    _ => println!("Error: Unknown transport error"),
+                }
+                /* end of input on the host, no selection will follow */
+                #[cfg(feature = "std")]
+                if let Error::NoDownlink = e {
+                    crate::report_exit(false);
+                }
+                ""
             },
         };
-        return test_index;
+
+        if command == RUN_ALL_COMMAND {
+            return Selection::RunAll;
+        }
+        match command.parse::<u16>() {
+            Ok(i) if i < n_tests => return Selection::Test(i),
+            Ok(_) => println!("Error: Test index out of range, select [0..{}]:", n_tests.saturating_sub(1)),
+            Err(_) => println!("Error: Could not parse test index"),
+        }
     }
 }
 
@@ -78,7 +90,7 @@ macro_rules! term_gray {
 #[cfg(not(feature = "colored"))]
 # [doc(hidden)]
 # [macro_export]
-macro_rules ! term_reset { ($string:expr) => { $string } }
+macro_rules ! term_reset { () => { "" } }
 
 #[cfg(not(feature = "colored"))]
 # [doc(hidden)]
@@ -0,0 +1,56 @@
+//! ITM/SWO transport (Cortex-M).
+//!
+//! Routes test output to an ITM stimulus port, so results can be captured via
+//! SWO without consuming a peripheral. ITM is output only, interactive mode is
+//! not available.
+//!
+//! # Example
+//! ```ignore
+//! #[cortex_m_rt::entry]
+//! fn main() -> ! {
+//!     let cp = cortex_m::Peripherals::take().unwrap();
+//!
+//!     // Register ITM stimulus port 0 as transport
+//!     bern_test::itm::init(cp.ITM, 0);
+//!     /*...*/
+//! }
+//! ```
+use core::ptr;
+use cortex_m::peripheral::ITM;
+use crate::transport::{self, Error, Transport};
+
+/// ITM stimulus port.
+pub struct Itm {
+    itm: ITM,
+    port: usize,
+}
+
+static mut ITM_TRANSPORT: Option<Itm> = None;
+
+/// Register an ITM stimulus port (0..32) as transport.
+///
+/// The stimulus port is enabled, ITM and the SWO pin must be configured by the
+/// debugger.
+pub fn init(itm: ITM, port: u8) {
+    let port = port as usize;
+    assert!(port < 32, "ITM stimulus port out of range");
+    unsafe { itm.ter[0].modify(|ter| ter | (1 << port)); }
+    let itm = critical_section::with(|_| unsafe {
+        let itm_transport = &mut *ptr::addr_of_mut!(ITM_TRANSPORT);
+        *itm_transport = Some(Itm { itm, port });
+        itm_transport.as_mut().unwrap()
+    });
+    transport::set_transport(itm);
+}
+
+impl Transport for Itm {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let stim = &mut self.itm.stim[self.port];
+        for b in bytes {
+            /* wait until the port can take another byte */
+            while !stim.is_fifo_ready() {}
+            stim.write_u8(*b);
+        }
+        Ok(())
+    }
+}
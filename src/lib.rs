@@ -4,83 +4,231 @@
 //!
 //! # Features
 //! - `autorun`: Run tests without user interaction
+//! - `in-process`: Run all tests in one boot, the device is only reset after
+//!   a panic (see [`reset::restart`])
+//! - `sys-reset`: Restart with `SCB::sys_reset()` after a panic (Cortex-M)
 //! - `serial`: Use serial interface for transport
-//! - `rtt`: Use RTT for transport
+//! - `hal`: Use an embedded-hal 0.2 serial peripheral for transport
+//! - `io`: Use an embedded-io (embedded-hal 1.0) serial peripheral for transport
+//! - `buffered`: Interrupt driven serial transport
+//! - `rtt`: Use RTT for transport (call [`rtt::init`] first)
+//! - `semihosting`: Use ARM semihosting for transport (call
+//!   [`semihosting::init`] first) and report the result with `SYS_EXIT`
+//! - `itm`: Use an ITM stimulus port for output (call [`itm::init`] first)
+//! - `std`: Run tests natively on the host (standard output and input,
+//!   panics are caught instead of resetting the device)
 //! - `colored`: Use terminal colors
+//! - `defmt`: Emit the test report as defmt frames
+//! - `machine`: Emit the test report as checksummed records for host tools
+//! - `tap`: Emit the test report in TAP (Test Anything Protocol) format
+//! - `json`: Emit the test report as libtest compatible JSON events (tests
+//!   must not print)
+//!
+//! # Transport
+//! Output and user input go through a [`transport::Transport`]. The serial
+//! interface is used by default, other transports (e.g. RTT, USB CDC) can be
+//! registered with [`transport::set_transport`].
 
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 #[cfg(feature = "serial")]
 pub mod serial;
-#[cfg(feature = "serial")]
+#[cfg(feature = "rtt")]
+pub mod rtt;
+#[cfg(feature = "semihosting")]
+pub mod semihosting;
+#[cfg(feature = "itm")]
+pub mod itm;
+#[cfg(feature = "std")]
+pub mod stdio;
+pub mod transport;
+pub mod watchdog;
+pub mod reset;
 #[doc(hidden)]
 pub mod console;
 #[doc(hidden)]
 pub mod run_all;
+#[doc(hidden)]
+pub mod report;
+mod message;
 
 pub use bern_test_macros::tests;
 
 #[cfg(feature = "rtt")]
 pub use rtt_target;
 
-use core::panic::PanicInfo;
+use core::fmt::{Debug, Display, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+use run_all::Outcome;
+pub use message::PANIC_MESSAGE_CAPACITY;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use stdio::{run_catching, set_panic_hook};
+
+/// Maximum number of tests in a test module (indices are `u16`).
+pub const MAX_TESTS: usize = u16::MAX as usize;
+
+/* set from the panic handler until the test tear down completed */
+static PANICKING: AtomicBool = AtomicBool::new(false);
+/* result of the last test, exit code of a single test on the host */
+static LAST_FAILED: AtomicBool = AtomicBool::new(false);
 
 #[doc(hidden)]
 pub fn test_succeeded() {
-    println!(term_green!("ok"));
-    run_all::test_succeeded();
+    LAST_FAILED.store(false, Ordering::SeqCst);
+    run_all::test_finished(Outcome::Passed);
+    report::test_succeeded();
 }
 
 #[doc(hidden)]
-pub fn test_failed(message: &str) {
-    println!(term_red!("FAILED"));
-    println!("{}", message);
+pub fn test_failed(message: &dyn Display) {
+    LAST_FAILED.store(true, Ordering::SeqCst);
+    run_all::test_finished(Outcome::Failed);
+    report::test_failed(message);
 }
 
 #[doc(hidden)]
-pub fn test_panicked(info: &PanicInfo) {
-    println!(term_red!("FAILED"));
-    println!(" └─ stdout:\n{}", info);
+pub fn test_panicked(info: &dyn Display) {
+    LAST_FAILED.store(true, Ordering::SeqCst);
+    run_all::test_finished(Outcome::Failed);
+    unsafe { transport::release_on_panic(); }
+    report::test_panicked(info);
 }
 
-#[cfg(feature = "serial")]
-#[macro_export]
-macro_rules! println {
-    ($($args:tt)*) => {
-        {
-            $crate::sprintln!($($args)*);
+/// Report a test of a run over all tests that was in progress when the
+/// device was reset (watchdog timeout or unexpected reset).
+#[doc(hidden)]
+pub fn report_interrupted(module: &'static str, names: &[&'static str]) {
+    if let Some((index, timed_out)) = run_all::take_interrupted() {
+        report::test_started(index, module, names.get(index as usize).unwrap_or(&"?"));
+        if timed_out {
+            run_all::set_outcome(index, Outcome::TimedOut);
+            report::test_timed_out();
+        } else {
+            run_all::set_outcome(index, Outcome::Reset);
+            report::test_reset(reset::cause());
         }
     }
 }
 
-#[cfg(feature = "serial")]
-#[macro_export]
-macro_rules! print {
-    ($($args:tt)*) => {
-        {
-            $crate::sprint!($($args)*);
+/// No test with `index` exists, reported as failure.
+#[doc(hidden)]
+pub fn test_not_found(index: u16) {
+    LAST_FAILED.store(true, Ordering::SeqCst);
+    println!("Error: No test with index {}", index);
+}
+
+/// An ignored test was skipped in a run over all tests.
+#[doc(hidden)]
+pub fn test_ignored(index: u16, module: &str, name: &str) {
+    run_all::set_outcome(index, Outcome::Ignored);
+    report::test_ignored(index, module, name);
+}
+
+/// Return type of a test function: `()` or `Result<(), E>` with
+/// `E: Debug`.
+///
+/// An `Err` fails the test with the formatted error, without a panic.
+pub trait TestReturn {
+    /// Report an error, returns whether the test completed without error.
+    #[doc(hidden)]
+    fn check(self) -> bool;
+}
+
+impl TestReturn for () {
+    fn check(self) -> bool {
+        true
+    }
+}
+
+impl<E: Debug> TestReturn for Result<(), E> {
+    fn check(self) -> bool {
+        match self {
+            Ok(()) => true,
+            Err(e) => {
+                test_failed(&format_args!("Error: {:?}", e));
+                false
+            },
         }
     }
 }
 
-#[cfg(feature = "rtt")]
+/// A `should_panic` test panicked, the test passes if the panic message
+/// (without location, truncated to [`PANIC_MESSAGE_CAPACITY`]) contains
+/// `expected`.
+#[doc(hidden)]
+pub fn test_panicked_expected(panic_message: &dyn Display, expected: &str) {
+    let mut message = message::MessageBuffer::new();
+    write!(message, "{}", panic_message).ok();
+    if message.as_str().contains(expected) {
+        test_succeeded();
+    } else {
+        LAST_FAILED.store(true, Ordering::SeqCst);
+        run_all::test_finished(Outcome::Failed);
+        unsafe { transport::release_on_panic(); }
+        report::test_failed(&message::Mismatch {
+            message: message.as_str(),
+            expected,
+        });
+    }
+}
+
+/// Print a line to the registered transport.
 #[macro_export]
 macro_rules! println {
-    ($($args:tt)*) => {
+    () => {
         {
-            rtt_target::rprintln!($($args)*);
+            $crate::transport::write_str("\r\n");
         }
-    }
+    };
+    ($fmt:expr) => {
+        {
+            $crate::transport::write_str(concat!($fmt, "\r\n"));
+        }
+    };
+    ($fmt:expr, $($arg:tt)*) => {
+        {
+            $crate::transport::write_fmt(format_args!(concat!($fmt, "\r\n"), $($arg)*));
+        }
+    };
 }
 
-#[cfg(feature = "rtt")]
+/// Print to the registered transport.
 #[macro_export]
 macro_rules! print {
-    ($($args:tt)*) => {
+    ($fmt:expr) => {
         {
-            rtt_target::rprint!($($args)*);
+            $crate::transport::write_str($fmt);
         }
-    }
+    };
+    ($fmt:expr, $($arg:tt)*) => {
+        {
+            $crate::transport::write_fmt(format_args!($fmt, $($arg)*));
+        }
+    };
+}
+
+/// Send any pending output, blocking.
+#[doc(hidden)]
+pub fn flush() {
+    transport::flush();
+}
+
+/// Report the result of a run over all tests to the host.
+#[doc(hidden)]
+pub fn report_exit(success: bool) {
+    #[cfg(feature = "semihosting")]
+    semihosting::exit(success);
+    #[cfg(feature = "std")]
+    std::process::exit(if success { 0 } else { 1 });
+    #[cfg(not(any(feature = "semihosting", feature = "std")))]
+    let _ = success;
+}
+
+/// Whether the last test passed.
+#[doc(hidden)]
+pub fn last_test_succeeded() -> bool {
+    !LAST_FAILED.load(Ordering::SeqCst)
 }
 
 #[doc(hidden)]
@@ -94,4 +242,34 @@ pub fn is_autorun_enabled() -> bool {
     return true;
     #[cfg(not(feature = "autorun"))]
     return false;
+}
+
+#[doc(hidden)]
+pub fn is_in_process_enabled() -> bool {
+    #[cfg(feature = "in-process")]
+    return true;
+    #[cfg(not(feature = "in-process"))]
+    return false;
+}
+
+/// Whether the current test panicked.
+///
+/// In in-process mode `#[test_tear_down]` runs after every test in the same
+/// boot, after a panic it runs before the device is restarted (see
+/// [`reset::restart`]):
+/// ```ignore
+/// #[test_tear_down]
+/// fn clean_up() {
+///     if !bern_test::is_panicking() {
+///         board().led.set_low().ok();
+///     }
+/// }
+/// ```
+pub fn is_panicking() -> bool {
+    PANICKING.load(Ordering::SeqCst)
+}
+
+#[doc(hidden)]
+pub fn set_panicking(panicking: bool) {
+    PANICKING.store(panicking, Ordering::SeqCst);
 }
\ No newline at end of file
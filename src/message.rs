@@ -0,0 +1,56 @@
+//! Panic message matching for `#[should_panic(expected = "...")]`.
+use core::fmt::{self, Display, Write};
+
+/// Size of the buffer a panic message is formatted into, longer messages are
+/// truncated before matching.
+pub const PANIC_MESSAGE_CAPACITY: usize = 256;
+
+/// Fixed size buffer, output beyond the capacity is discarded.
+pub(crate) struct MessageBuffer {
+    buffer: [u8; PANIC_MESSAGE_CAPACITY],
+    len: usize,
+}
+
+impl MessageBuffer {
+    pub(crate) fn new() -> Self {
+        MessageBuffer {
+            buffer: [0; PANIC_MESSAGE_CAPACITY],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        let bytes = &self.buffer[..self.len];
+        /* truncation can split a character */
+        match core::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(e) => unsafe { core::str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) },
+        }
+    }
+}
+
+impl Write for MessageBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = s.len().min(PANIC_MESSAGE_CAPACITY - self.len);
+        self.buffer[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// Failure message if a panic did not contain the expected string.
+pub(crate) struct Mismatch<'a> {
+    pub(crate) message: &'a str,
+    pub(crate) expected: &'a str,
+}
+
+impl Display for Mismatch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "panic did not contain expected string\n      panic message: `{}`\n expected substring: `{}`",
+            self.message,
+            self.expected,
+        )
+    }
+}
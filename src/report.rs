@@ -0,0 +1,186 @@
+//! Test report output.
+//!
+//! The generated runner emits all output through these functions, so the
+//! output format can be changed in one place. The format is selected with a
+//! cargo feature:
+//! - default: human readable text, optionally colored
+//! - `defmt`: defmt frames (interned strings)
+//! - `machine`: versioned, checksummed records for host tools, see
+//!   [`machine`]
+//! - `tap`: Test Anything Protocol version 13
+//! - `json`: libtest compatible JSON events
+use core::fmt::Display;
+#[cfg(any(feature = "tap", feature = "json"))]
+use core::ptr;
+use core::sync::atomic::{AtomicU16, Ordering};
+use crate::run_all::{self, Outcome};
+
+#[cfg(any(
+    all(feature = "defmt", any(feature = "machine", feature = "tap", feature = "json")),
+    all(feature = "machine", any(feature = "tap", feature = "json")),
+    all(feature = "tap", feature = "json"),
+))]
+compile_error!("select only one output format: `defmt`, `machine`, `tap` or `json`");
+
+#[cfg(feature = "defmt")]
+mod defmt_frames;
+#[cfg(feature = "defmt")]
+use defmt_frames as format;
+#[cfg(feature = "machine")]
+pub mod machine;
+#[cfg(all(feature = "machine", not(feature = "defmt")))]
+use machine as format;
+#[cfg(feature = "tap")]
+mod tap;
+#[cfg(all(feature = "tap", not(any(feature = "defmt", feature = "machine"))))]
+use tap as format;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(all(feature = "json", not(any(feature = "defmt", feature = "machine", feature = "tap"))))]
+use json as format;
+#[cfg(not(any(feature = "defmt", feature = "machine", feature = "tap", feature = "json")))]
+mod text;
+#[cfg(not(any(feature = "defmt", feature = "machine", feature = "tap", feature = "json")))]
+use text as format;
+
+/* index and name of the current test, a result is reported in the same
+ * boot */
+static CURRENT_TEST: AtomicU16 = AtomicU16::new(0);
+#[cfg(any(feature = "tap", feature = "json"))]
+static mut CURRENT_NAME: (&str, &str) = ("", "");
+
+/// Module and name of the current test, for formats that repeat the name
+/// with the result.
+#[cfg(any(feature = "tap", feature = "json"))]
+fn current_name() -> (&'static str, &'static str) {
+    critical_section::with(|_| unsafe { *ptr::addr_of!(CURRENT_NAME) })
+}
+
+/// Transport statistics reported with the summary.
+struct Stats {
+    nested: u32,
+    tx_dropped: u32,
+    rx_dropped: u32,
+}
+
+/// Failed tests of a run over all tests.
+#[cfg_attr(any(feature = "machine", feature = "json"), allow(dead_code))]
+struct Failures<'a> {
+    module: &'a str,
+    names: &'a [&'a str],
+}
+
+#[cfg_attr(any(feature = "machine", feature = "json"), allow(dead_code))]
+impl<'a> Failures<'a> {
+    /// Module, name and failure reason (empty for a failed test) of every
+    /// test that did not pass and was not ignored.
+    fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str, &'static str)> + '_ {
+        let module = self.module;
+        self.names.iter()
+            .enumerate()
+            .filter_map(move |(i, name)| {
+                let reason = match run_all::get_outcome(i as u16) {
+                    Outcome::Passed | Outcome::Ignored => return None,
+                    Outcome::Failed => "",
+                    Outcome::TimedOut => " (timeout)",
+                    Outcome::Reset => " (unexpected reset)",
+                    Outcome::NotRun => " (not run)",
+                };
+                Some((module, *name, reason))
+            })
+    }
+}
+
+impl Stats {
+    fn get() -> Self {
+        #[cfg(feature = "buffered")]
+        let (tx_dropped, rx_dropped) = (
+            crate::serial::buffer::tx_dropped(),
+            crate::serial::buffer::rx_dropped(),
+        );
+        #[cfg(not(feature = "buffered"))]
+        let (tx_dropped, rx_dropped) = (0, 0);
+        Stats {
+            nested: crate::transport::nested_dropped(),
+            tx_dropped,
+            rx_dropped,
+        }
+    }
+}
+
+/// Print the runner header.
+pub fn header() {
+    format::header();
+}
+
+/// Print one entry of the test list in interactive mode.
+pub fn list_test(index: u16, module: &str, name: &str, ignored: bool) {
+    format::list_test(index, module, name, ignored);
+}
+
+/// Print the end of the test list and the selection prompt.
+pub fn list_footer(n_tests: u16) {
+    format::list_footer(n_tests);
+}
+
+/// The state of a previous run over all tests was discarded.
+pub fn state_rejected(reason: &str) {
+    format::state_rejected(reason);
+}
+
+/// A run over all tests (including ignored tests) started.
+pub fn run_started(n_tests: u16) {
+    format::run_started(n_tests);
+}
+
+/// Index of the current test.
+pub fn current_test() -> u16 {
+    CURRENT_TEST.load(Ordering::Relaxed)
+}
+
+/// A test started.
+pub fn test_started(index: u16, module: &'static str, name: &'static str) {
+    CURRENT_TEST.store(index, Ordering::Relaxed);
+    #[cfg(any(feature = "tap", feature = "json"))]
+    critical_section::with(|_| unsafe { *ptr::addr_of_mut!(CURRENT_NAME) = (module, name) });
+    format::test_started(index, module, name);
+}
+
+/// The current test succeeded.
+pub fn test_succeeded() {
+    format::test_succeeded(CURRENT_TEST.load(Ordering::Relaxed));
+}
+
+/// The current test failed.
+pub fn test_failed(message: &dyn Display) {
+    format::test_failed(CURRENT_TEST.load(Ordering::Relaxed), message);
+}
+
+/// The current test panicked.
+pub fn test_panicked(info: &dyn Display) {
+    format::test_panicked(CURRENT_TEST.load(Ordering::Relaxed), info);
+}
+
+/// The current test timed out.
+pub fn test_timed_out() {
+    format::test_timed_out(CURRENT_TEST.load(Ordering::Relaxed));
+}
+
+/// The device was reset unexpectedly during the current test.
+pub fn test_reset(reason: &str) {
+    format::test_reset(CURRENT_TEST.load(Ordering::Relaxed), reason);
+}
+
+/// An ignored test was skipped.
+pub fn test_ignored(index: u16, module: &str, name: &str) {
+    format::test_ignored(index, module, name);
+}
+
+/// Print the result of a run over all tests from the outcome table.
+pub fn summary(module: &str, names: &[&str]) {
+    let passed = run_all::count(Outcome::Passed);
+    let ignored = run_all::count(Outcome::Ignored);
+    let failed = (names.len() as u16).saturating_sub(passed + ignored);
+    let failures = Failures { module, names };
+    format::summary(passed, failed, ignored, &failures, &Stats::get());
+}
@@ -0,0 +1,92 @@
+//! defmt frames output.
+use core::fmt::Display;
+use super::{Failures, Stats};
+
+pub(super) fn header() {
+    defmt::println!("~~~~~~~~~~~~~~ Bern Test v{=str} ~~~~~~~~~~~~~~", crate::get_version());
+}
+
+pub(super) fn list_test(index: u16, module: &str, name: &str, ignored: bool) {
+    defmt::println!(
+        "[{=u16}] {=str}::{=str}{=str}",
+        index,
+        module,
+        name,
+        if ignored { " (ignored)" } else { "" },
+    );
+}
+
+pub(super) fn list_footer(n_tests: u16) {
+    defmt::println!("[{=str}] run all tests", crate::console::RUN_ALL_COMMAND);
+    defmt::println!("Select test [0..{=u16}]:", n_tests.saturating_sub(1));
+}
+
+pub(super) fn state_rejected(reason: &str) {
+    defmt::error!("run over all tests not resumed: state {=str}", reason);
+}
+
+pub(super) fn run_started(n_tests: u16) {
+    defmt::println!("running {=u16} tests", n_tests);
+}
+
+pub(super) fn test_started(_index: u16, module: &str, name: &str) {
+    defmt::println!("test {=str}::{=str} ...", module, name);
+}
+
+pub(super) fn test_succeeded(_index: u16) {
+    defmt::info!("ok");
+}
+
+pub(super) fn test_failed(_index: u16, message: &dyn Display) {
+    defmt::error!("FAILED\n └─ {}", defmt::Display2Format(message));
+}
+
+pub(super) fn test_panicked(_index: u16, info: &dyn Display) {
+    defmt::error!("FAILED\n └─ stdout:\n{}", defmt::Display2Format(info));
+}
+
+pub(super) fn test_timed_out(_index: u16) {
+    defmt::error!("FAILED (timeout)");
+}
+
+pub(super) fn test_reset(_index: u16, reason: &str) {
+    defmt::error!("FAILED (unexpected reset, reason: {=str})", reason);
+}
+
+pub(super) fn test_ignored(_index: u16, module: &str, name: &str) {
+    defmt::println!("test {=str}::{=str} ... ignored", module, name);
+}
+
+pub(super) fn summary(passed: u16, failed: u16, ignored: u16, failures: &Failures, stats: &Stats) {
+    if failed > 0 {
+        defmt::println!("failures:");
+        for (module, name, reason) in failures.iter() {
+            defmt::println!("    {=str}::{=str}{=str}", module, name, reason);
+        }
+    }
+    if failed == 0 {
+        defmt::info!(
+            "test result: ok. {=u16} passed; {=u16} failed; {=u16} ignored",
+            passed,
+            failed,
+            ignored,
+        );
+    } else {
+        defmt::error!(
+            "test result: FAILED. {=u16} passed; {=u16} failed; {=u16} ignored",
+            passed,
+            failed,
+            ignored,
+        );
+    }
+    if stats.nested > 0 {
+        defmt::warn!("transport: {=u32} nested accesses dropped", stats.nested);
+    }
+    if stats.tx_dropped > 0 || stats.rx_dropped > 0 {
+        defmt::warn!(
+            "serial buffer overflow: {=u32} bytes dropped (tx); {=u32} bytes dropped (rx)",
+            stats.tx_dropped,
+            stats.rx_dropped,
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! libtest compatible JSON event stream.
+//!
+//! One JSON object per line, as `cargo test -- -Z unstable-options --format
+//! json` prints them, so host tools can forward the stream unchanged:
+//! ```text
+//! { "type": "suite", "event": "started", "test_count": 2 }
+//! { "type": "test", "event": "started", "name": "tests::first" }
+//! { "type": "test", "name": "tests::first", "event": "ok" }
+//! { "type": "test", "event": "started", "name": "tests::second" }
+//! { "type": "test", "name": "tests::second", "event": "failed", "stdout": "panicked at ..." }
+//! { "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 0, "measured": 0, "filtered_out": 0, ... }
+//! ```
+//! The test list and prompt in interactive mode stay human readable.
+//!
+//! Output of the tests (`println!`) is not captured and would end up between
+//! the events, tests must not print in this mode.
+use core::fmt::{self, Display, Write};
+use crate::println;
+use crate::transport::{self, Transport, Writer};
+use super::{current_name, Failures, Stats};
+
+/// Escapes the content of a JSON string.
+struct Escape<'a>(&'a mut dyn Transport);
+
+impl Write for Escape<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            let escaped: &[u8] = match c {
+                '"' => b"\\\"",
+                '\\' => b"\\\\",
+                '\n' => b"\\n",
+                '\r' => b"\\r",
+                '\t' => b"\\t",
+                c if (c as u32) < 0x20 => b"",
+                _ => continue,
+            };
+            self.0.write(&s.as_bytes()[start..i]).map_err(|_| fmt::Error)?;
+            if escaped.is_empty() {
+                write!(Writer(self.0), "\\u{:04x}", c as u32)?;
+            } else {
+                self.0.write(escaped).map_err(|_| fmt::Error)?;
+            }
+            start = i + c.len_utf8();
+        }
+        self.0.write(&s.as_bytes()[start..]).map_err(|_| fmt::Error)
+    }
+}
+
+fn failed(message: &dyn Display) {
+    let (module, name) = current_name();
+    transport::with(|t| {
+        write!(
+            Writer(t),
+            "{{ \"type\": \"test\", \"name\": \"{}::{}\", \"event\": \"failed\", \"stdout\": \"",
+            module,
+            name,
+        ).ok();
+        write!(Escape(t), "{}", message).ok();
+        t.write(b"\" }\r\n").ok();
+    });
+}
+
+pub(super) fn header() {
+    /* not part of the event stream */
+}
+
+pub(super) fn list_test(index: u16, module: &str, name: &str, ignored: bool) {
+    println!("[{}] {}::{}{}", index, module, name, if ignored { " (ignored)" } else { "" });
+}
+
+pub(super) fn list_footer(n_tests: u16) {
+    println!("[{}] run all tests", crate::console::RUN_ALL_COMMAND);
+    println!("Select test [0..{}]:", n_tests.saturating_sub(1));
+}
+
+pub(super) fn state_rejected(_reason: &str) {
+    /* not part of the event stream, a new suite is started */
+}
+
+pub(super) fn run_started(n_tests: u16) {
+    println!("{{ \"type\": \"suite\", \"event\": \"started\", \"test_count\": {} }}", n_tests);
+}
+
+pub(super) fn test_started(_index: u16, module: &str, name: &str) {
+    println!("{{ \"type\": \"test\", \"event\": \"started\", \"name\": \"{}::{}\" }}", module, name);
+}
+
+pub(super) fn test_succeeded(_index: u16) {
+    let (module, name) = current_name();
+    println!("{{ \"type\": \"test\", \"name\": \"{}::{}\", \"event\": \"ok\" }}", module, name);
+}
+
+pub(super) fn test_failed(_index: u16, message: &dyn Display) {
+    failed(message);
+}
+
+pub(super) fn test_panicked(_index: u16, info: &dyn Display) {
+    failed(info);
+}
+
+pub(super) fn test_timed_out(_index: u16) {
+    failed(&"timeout");
+}
+
+pub(super) fn test_reset(_index: u16, reason: &str) {
+    failed(&format_args!("unexpected reset, reason: {}", reason));
+}
+
+pub(super) fn test_ignored(index: u16, module: &str, name: &str) {
+    /* libtest starts ignored tests as well */
+    test_started(index, module, name);
+    println!("{{ \"type\": \"test\", \"name\": \"{}::{}\", \"event\": \"ignored\" }}", module, name);
+}
+
+pub(super) fn summary(passed: u16, failed: u16, ignored: u16, _failures: &Failures, stats: &Stats) {
+    /* failures are known from the `failed` events */
+    /* transport statistics are additional fields, ignored by libtest tools */
+    println!(
+        concat!(
+            "{{ \"type\": \"suite\", \"event\": \"{}\", \"passed\": {}, \"failed\": {}, ",
+            "\"ignored\": {}, \"measured\": 0, \"filtered_out\": 0, ",
+            "\"nested_dropped\": {}, \"tx_dropped\": {}, \"rx_dropped\": {} }}",
+        ),
+        if failed == 0 { "ok" } else { "failed" },
+        passed,
+        failed,
+        ignored,
+        stats.nested,
+        stats.tx_dropped,
+        stats.rx_dropped,
+    );
+}
@@ -0,0 +1,158 @@
+//! Machine readable line protocol.
+//!
+//! Every event is one record, any other output (e.g. from a test) is not a
+//! record and can be ignored by host tools:
+//! ```text
+//! #BT:<TYPE> <fields>*<CRC>\r\n
+//! ```
+//! `CRC` is the CRC-16/CCITT-FALSE (4 uppercase hex digits) of all bytes from
+//! `#` up to `*`. Messages are length prefixed (bytes) and may span multiple
+//! lines.
+//!
+//! | Record                               | Event                        |
+//! |--------------------------------------|------------------------------|
+//! | `#BT:HELLO <protocol> <version>`     | runner header                |
+//! | `#BT:LIST <idx> <name> [ignored]`    | test list entry              |
+//! | `#BT:SELECT <n>`                     | waiting for test selection   |
+//! | `#BT:REJECT <reason>`                | previous run not resumed     |
+//! | `#BT:RUN <n>`                        | run over all tests started   |
+//! | `#BT:START <idx> <name>`             | test started                 |
+//! | `#BT:PASS <idx>`                     | test passed                  |
+//! | `#BT:FAIL <idx> <len> <msg>`         | test failed                  |
+//! | `#BT:IGNORE <idx> <name>`            | ignored test skipped         |
+//! | `#BT:SUMMARY <pass> <fail> <ignore>` | result of run over all tests |
+//! | `#BT:STATS <nested> <tx> <rx>`       | dropped accesses and bytes   |
+use core::fmt::{self, Display, Write};
+use crate::transport::{self, Transport};
+use super::{Failures, Stats};
+
+/// Protocol version of the records.
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Update a CRC-16/CCITT-FALSE (initial value `0xFFFF`).
+pub fn crc16(mut crc: u16, bytes: &[u8]) -> u16 {
+    for b in bytes {
+        crc ^= (*b as u16) << 8;
+        for _ in 0..8 {
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x1021,
+            };
+        }
+    }
+    crc
+}
+
+/// Writes a record to the transport while computing its checksum.
+struct Record<'a> {
+    transport: &'a mut dyn Transport,
+    crc: u16,
+}
+
+impl Write for Record<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.crc = crc16(self.crc, s.as_bytes());
+        self.transport.write(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+/// Counts the bytes of formatted output.
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// Write one complete record, the transport is locked for the whole record.
+fn record(args: fmt::Arguments) {
+    transport::with(|transport| {
+        let mut record = Record {
+            transport,
+            crc: 0xFFFF,
+        };
+        record.write_fmt(args).ok();
+        let crc = record.crc;
+        let mut end = [0u8; 7];
+        end[0] = b'*';
+        for (i, shift) in [12, 8, 4, 0].iter().enumerate() {
+            end[i + 1] = b"0123456789ABCDEF"[((crc >> shift) & 0xF) as usize];
+        }
+        end[5] = b'\r';
+        end[6] = b'\n';
+        record.transport.write(&end).ok();
+    });
+}
+
+fn failed(index: u16, message: &dyn Display) {
+    let mut len = Counter(0);
+    write!(len, "{}", message).ok();
+    record(format_args!("#BT:FAIL {} {} {}", index, len.0, message));
+}
+
+pub(super) fn header() {
+    record(format_args!("#BT:HELLO {} {}", PROTOCOL_VERSION, crate::get_version()));
+}
+
+pub(super) fn list_test(index: u16, module: &str, name: &str, ignored: bool) {
+    record(format_args!(
+        "#BT:LIST {} {}::{}{}",
+        index,
+        module,
+        name,
+        if ignored { " ignored" } else { "" },
+    ));
+}
+
+pub(super) fn list_footer(n_tests: u16) {
+    record(format_args!("#BT:SELECT {}", n_tests));
+}
+
+pub(super) fn state_rejected(reason: &str) {
+    record(format_args!("#BT:REJECT {}", reason));
+}
+
+pub(super) fn run_started(n_tests: u16) {
+    record(format_args!("#BT:RUN {}", n_tests));
+}
+
+pub(super) fn test_started(index: u16, module: &str, name: &str) {
+    record(format_args!("#BT:START {} {}::{}", index, module, name));
+}
+
+pub(super) fn test_succeeded(index: u16) {
+    record(format_args!("#BT:PASS {}", index));
+}
+
+pub(super) fn test_failed(index: u16, message: &dyn Display) {
+    failed(index, message);
+}
+
+pub(super) fn test_panicked(index: u16, info: &dyn Display) {
+    failed(index, info);
+}
+
+pub(super) fn test_timed_out(index: u16) {
+    failed(index, &"timeout");
+}
+
+pub(super) fn test_reset(index: u16, reason: &str) {
+    failed(index, &format_args!("unexpected reset, reason: {}", reason));
+}
+
+pub(super) fn test_ignored(index: u16, module: &str, name: &str) {
+    record(format_args!("#BT:IGNORE {} {}::{}", index, module, name));
+}
+
+pub(super) fn summary(passed: u16, failed: u16, ignored: u16, _failures: &Failures, stats: &Stats) {
+    /* failures are known from the `FAIL` records */
+    record(format_args!("#BT:SUMMARY {} {} {}", passed, failed, ignored));
+    record(format_args!(
+        "#BT:STATS {} {} {}",
+        stats.nested,
+        stats.tx_dropped,
+        stats.rx_dropped,
+    ));
+}
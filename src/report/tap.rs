@@ -0,0 +1,123 @@
+//! TAP (Test Anything Protocol) version 13 output.
+//!
+//! ```text
+//! TAP version 13
+//! 1..3
+//! ok 1 - tests::first
+//! not ok 2 - tests::second
+//!   ---
+//!   message: |
+//!     panicked at src/main.rs:16:18:
+//!     assertion failed
+//!   ...
+//! ok 3 - tests::skipped # SKIP
+//! ```
+//! Test numbers start at 1 (index + 1). Interactive mode output and
+//! statistics are TAP comments.
+use core::fmt::{self, Display, Write};
+use crate::println;
+use crate::transport::{self, Transport, Writer};
+use super::{current_name, Failures, Stats};
+
+/// Indents every line of a YAML block scalar.
+struct Indent<'a>(&'a mut dyn Transport);
+
+impl Write for Indent<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.0.write(b"\r\n    ").map_err(|_| fmt::Error)?;
+            }
+            self.0.write(line.trim_end_matches('\r').as_bytes()).map_err(|_| fmt::Error)?;
+        }
+        Ok(())
+    }
+}
+
+fn not_ok(index: u16, message: &dyn Display) {
+    let (module, name) = current_name();
+    transport::with(|t| {
+        write!(
+            Writer(t),
+            "not ok {} - {}::{}\r\n  ---\r\n  message: |\r\n    ",
+            index + 1,
+            module,
+            name,
+        ).ok();
+        write!(Indent(t), "{}", message).ok();
+        t.write(b"\r\n  ...\r\n").ok();
+    });
+}
+
+pub(super) fn header() {
+    println!("TAP version 13");
+    println!("# Bern Test v{}", crate::get_version());
+}
+
+pub(super) fn list_test(index: u16, module: &str, name: &str, ignored: bool) {
+    println!("# [{}] {}::{}{}", index, module, name, if ignored { " (ignored)" } else { "" });
+}
+
+pub(super) fn list_footer(n_tests: u16) {
+    println!("# [{}] run all tests", crate::console::RUN_ALL_COMMAND);
+    println!("# Select test [0..{}]:", n_tests.saturating_sub(1));
+}
+
+pub(super) fn state_rejected(reason: &str) {
+    println!("# run over all tests not resumed: state {}", reason);
+}
+
+pub(super) fn run_started(n_tests: u16) {
+    println!("1..{}", n_tests);
+}
+
+pub(super) fn test_started(_index: u16, _module: &str, _name: &str) {
+    /* reported with the result */
+}
+
+pub(super) fn test_succeeded(index: u16) {
+    let (module, name) = current_name();
+    println!("ok {} - {}::{}", index + 1, module, name);
+}
+
+pub(super) fn test_failed(index: u16, message: &dyn Display) {
+    not_ok(index, message);
+}
+
+pub(super) fn test_panicked(index: u16, info: &dyn Display) {
+    not_ok(index, info);
+}
+
+pub(super) fn test_timed_out(index: u16) {
+    not_ok(index, &"timeout");
+}
+
+pub(super) fn test_reset(index: u16, reason: &str) {
+    not_ok(index, &format_args!("unexpected reset, reason: {}", reason));
+}
+
+pub(super) fn test_ignored(index: u16, module: &str, name: &str) {
+    println!("ok {} - {}::{} # SKIP", index + 1, module, name);
+}
+
+pub(super) fn summary(passed: u16, failed: u16, ignored: u16, failures: &Failures, stats: &Stats) {
+    if failed > 0 {
+        println!("# failures:");
+        for (module, name, reason) in failures.iter() {
+            println!("#     {}::{}{}", module, name, reason);
+        }
+    }
+    println!("# pass {}", passed);
+    println!("# fail {}", failed);
+    println!("# skip {}", ignored);
+    if stats.nested > 0 {
+        println!("# transport: {} nested accesses dropped", stats.nested);
+    }
+    if stats.tx_dropped > 0 || stats.rx_dropped > 0 {
+        println!(
+            "# serial buffer overflow: {} bytes dropped (tx); {} bytes dropped (rx)",
+            stats.tx_dropped,
+            stats.rx_dropped,
+        );
+    }
+}
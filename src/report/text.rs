@@ -0,0 +1,86 @@
+//! Human readable text output.
+use core::fmt::Display;
+use crate::{println, print, term_green, term_red, term_reset};
+use super::{Failures, Stats};
+
+pub(super) fn header() {
+    println!(term_reset!());
+    println!("~~~~~~~~~~~~~~ Bern Test v{} ~~~~~~~~~~~~~~", crate::get_version());
+}
+
+pub(super) fn list_test(index: u16, module: &str, name: &str, ignored: bool) {
+    println!("[{}] {}::{}{}", index, module, name, if ignored { " (ignored)" } else { "" });
+}
+
+pub(super) fn list_footer(n_tests: u16) {
+    println!("[{}] run all tests", crate::console::RUN_ALL_COMMAND);
+    println!("Select test [0..{}]:", n_tests.saturating_sub(1));
+}
+
+pub(super) fn state_rejected(reason: &str) {
+    println!(term_red!("run over all tests not resumed: state {}"), reason);
+}
+
+pub(super) fn run_started(n_tests: u16) {
+    println!("\nrunning {} tests", n_tests);
+}
+
+pub(super) fn test_started(_index: u16, module: &str, name: &str) {
+    print!("test {}::{} ... ", module, name);
+}
+
+pub(super) fn test_succeeded(_index: u16) {
+    println!(term_green!("ok"));
+}
+
+pub(super) fn test_failed(_index: u16, message: &dyn Display) {
+    println!(term_red!("FAILED"));
+    println!(" └─ {}", message);
+}
+
+pub(super) fn test_panicked(_index: u16, info: &dyn Display) {
+    println!(term_red!("FAILED"));
+    println!(" └─ stdout:\n{}", info);
+}
+
+pub(super) fn test_timed_out(_index: u16) {
+    println!(term_red!("FAILED (timeout)"));
+}
+
+pub(super) fn test_reset(_index: u16, reason: &str) {
+    println!(term_red!("FAILED (unexpected reset, reason: {})"), reason);
+}
+
+pub(super) fn test_ignored(_index: u16, module: &str, name: &str) {
+    println!("test {}::{} ... ignored", module, name);
+}
+
+pub(super) fn summary(passed: u16, failed: u16, ignored: u16, failures: &Failures, stats: &Stats) {
+    if failed > 0 {
+        println!("\nfailures:");
+        for (module, name, reason) in failures.iter() {
+            println!("    {}::{}{}", module, name, reason);
+        }
+    }
+    let result = match failed {
+        0 => term_green!("ok"),
+        _ => term_red!("FAILED"),
+    };
+    println!(
+        "\ntest result: {}. {} passed; {} failed; {} ignored",
+        result,
+        passed,
+        failed,
+        ignored,
+    );
+    if stats.nested > 0 {
+        println!("transport: {} nested accesses dropped", stats.nested);
+    }
+    if stats.tx_dropped > 0 || stats.rx_dropped > 0 {
+        println!(
+            "serial buffer overflow: {} bytes dropped (tx); {} bytes dropped (rx)",
+            stats.tx_dropped,
+            stats.rx_dropped,
+        );
+    }
+}
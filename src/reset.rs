@@ -0,0 +1,71 @@
+//! Reset detection.
+//!
+//! In a run over all tests, the test in progress is marked in uninitialized
+//! RAM. If the device resets before the test reports a result (e.g.
+//! HardFault, watchdog or brown-out), the test is reported after reboot as
+//! `FAILED (unexpected reset, reason: ...)` and the run continues with the
+//! next test.
+//!
+//! The reason is read with an optional user hook, typically from the reset
+//! cause register of the MCU.
+//!
+//! After a panic the device is restarted with [`restart`], so a run over all
+//! tests continues even if `#[test_tear_down]` does not reset. The restart is
+//! done by a user hook or, with the `sys-reset` feature, by
+//! `SCB::sys_reset()` on Cortex-M.
+//!
+//! # Example
+//! ```ignore
+//! bern_test::reset::set_cause_hook(|| {
+//!     let rcc = unsafe { &*pac::RCC::ptr() };
+//!     let csr = rcc.csr.read();
+//!     if csr.wdgrstf().bit_is_set() {
+//!         "watchdog"
+//!     } else if csr.borrstf().bit_is_set() {
+//!         "brown-out"
+//!     } else {
+//!         "unknown"
+//!     }
+//! });
+//! bern_test::reset::set_restart_hook(|| cortex_m::peripheral::SCB::sys_reset());
+//! ```
+use core::ptr;
+use crate::transport;
+
+static mut CAUSE_HOOK: Option<fn() -> &'static str> = None;
+static mut RESTART_HOOK: Option<fn() -> !> = None;
+
+/// Set a hook returning the cause of the last reset.
+pub fn set_cause_hook(hook: fn() -> &'static str) {
+    critical_section::with(|_| unsafe { *ptr::addr_of_mut!(CAUSE_HOOK) = Some(hook) });
+}
+
+/// Cause of the last reset, `unknown` without hook.
+pub(crate) fn cause() -> &'static str {
+    match critical_section::with(|_| unsafe { *ptr::addr_of!(CAUSE_HOOK) }) {
+        Some(hook) => hook(),
+        None => "unknown",
+    }
+}
+
+/// Set a hook restarting the device, overrides the `sys-reset` feature.
+pub fn set_restart_hook(hook: fn() -> !) {
+    critical_section::with(|_| unsafe { *ptr::addr_of_mut!(RESTART_HOOK) = Some(hook) });
+}
+
+/// Flush pending output and restart the device.
+///
+/// Without hook and `sys-reset` feature this loops forever, the device must
+/// be reset externally (e.g. by the debugger).
+pub fn restart() -> ! {
+    transport::flush();
+    if let Some(hook) = critical_section::with(|_| unsafe { *ptr::addr_of!(RESTART_HOOK) }) {
+        hook();
+    }
+    #[cfg(feature = "sys-reset")]
+    cortex_m::peripheral::SCB::sys_reset();
+    #[cfg(not(feature = "sys-reset"))]
+    loop {
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,76 @@
+//! RTT transport.
+//!
+//! Sets up an RTT up channel for output and a down channel for user input, so
+//! a test can be selected interactively over a debug probe.
+//!
+//! # Example
+//! ```ignore
+//! #[cortex_m_rt::entry]
+//! fn main() -> ! {
+//!     let mut board = Board::new();
+//!
+//!     // Set up RTT channels and register them as transport (call once)
+//!     bern_test::rtt::init();
+//!     /*...*/
+//! }
+//! ```
+use core::ptr;
+use rtt_target::{rtt_init, DownChannel, UpChannel};
+use crate::transport::{self, Error, Transport};
+
+/// RTT channel 0 up and down.
+pub struct Rtt {
+    up: UpChannel,
+    down: DownChannel,
+}
+
+static mut RTT: Option<Rtt> = None;
+
+/// Initialize the RTT control block with channel 0 up and down and register
+/// it as transport.
+///
+/// The up channel blocks if it is full, so no test output is lost.
+pub fn init() {
+    let channels = rtt_init! {
+        up: {
+            0: {
+                size: 1024
+                mode: BlockIfFull
+                name: "Terminal"
+            }
+        }
+        down: {
+            0: {
+                size: 16
+                name: "Terminal"
+            }
+        }
+    };
+    let rtt = critical_section::with(|_| unsafe {
+        let rtt = &mut *ptr::addr_of_mut!(RTT);
+        *rtt = Some(Rtt {
+            up: channels.up.0,
+            down: channels.down.0,
+        });
+        rtt.as_mut().unwrap()
+    });
+    transport::set_transport(rtt);
+}
+
+impl Transport for Rtt {
+    fn write(&mut self, mut bytes: &[u8]) -> Result<(), Error> {
+        while !bytes.is_empty() {
+            let n = self.up.write(bytes);
+            bytes = &bytes[n..];
+        }
+        Ok(())
+    }
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        let mut byte = [0u8; 1];
+        match self.down.read(&mut byte) {
+            0 => Err(nb::Error::WouldBlock),
+            _ => Ok(byte[0]),
+        }
+    }
+}
@@ -1,37 +1,331 @@
-/* these must be put in a linker section that does get initialized */
-#[link_section = ".uninit"]
-static mut TEST_SECRET: u32 = 0;
-#[link_section = ".uninit"]
-static mut TEST_NEXT: u8 = 0;
+use core::{mem, ptr, slice};
+use crate::report;
+
+/* this must be put in a linker section that does get initialized */
 #[link_section = ".uninit"]
-static mut TEST_SUCCESSFUL: u8 = 0;
+static mut STATE: State = State::zeroed();
+/* outcome table of the test module (also in `.uninit`), set on every boot */
+static mut TEST_OUTCOMES: &mut [u8] = &mut [];
+/* test module of this firmware, set on every boot */
+static mut MODULE: (u16, u32) = (0, 0);
+
+const MAGIC: u32 = 0x12345678;
+/// Version of the [`State`] layout, increment on any change.
+const LAYOUT_VERSION: u16 = 2;
+
+/// State of a run over all tests, kept across resets.
+///
+/// The CRC covers the state and the outcome table, any change outside of
+/// [`update`] (e.g. brown-out, stack overflow) is detected.
+#[repr(C)]
+struct State {
+    magic: u32,
+    layout_version: u16,
+    /// Number of tests in the module.
+    n_tests: u16,
+    /// Hash of the test names, to detect a different firmware.
+    names_hash: u32,
+    next: u16,
+    /// Index + 1 of the test in progress, 0 if none.
+    running: u16,
+    /// 1 if the test in progress started the watchdog.
+    timeout: u8,
+    _reserved: [u8; 3],
+    crc: u32,
+}
+
+impl State {
+    const fn zeroed() -> Self {
+        State {
+            magic: 0,
+            layout_version: 0,
+            n_tests: 0,
+            names_hash: 0,
+            next: 0,
+            running: 0,
+            timeout: 0,
+            _reserved: [0; 3],
+            crc: 0,
+        }
+    }
+
+    fn crc(&self, outcomes: &[u8]) -> u32 {
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                self as *const State as *const u8,
+                mem::size_of::<State>() - mem::size_of::<u32>(),
+            )
+        };
+        !crc32(crc32(!0, bytes), outcomes)
+    }
+}
+
+/// Update a CRC-32 (IEEE, reflected).
+fn crc32(mut crc: u32, bytes: &[u8]) -> u32 {
+    for b in bytes {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                0 => crc >> 1,
+                _ => (crc >> 1) ^ 0xEDB8_8320,
+            };
+        }
+    }
+    crc
+}
+
+fn state() -> &'static State {
+    unsafe { &*ptr::addr_of!(STATE) }
+}
+
+fn outcomes() -> &'static [u8] {
+    unsafe { &*ptr::addr_of!(TEST_OUTCOMES) }
+}
 
-const SECRET_NUMBER: u32 = 0x12345678;
+/// Modify the state of an active run and update the CRC.
+fn update<F>(f: F)
+    where F: FnOnce(&mut State, &mut [u8])
+{
+    let state = unsafe { &mut *ptr::addr_of_mut!(STATE) };
+    if state.magic != MAGIC {
+        return;
+    }
+    let outcomes = unsafe { &mut *ptr::addr_of_mut!(TEST_OUTCOMES) };
+    f(state, outcomes);
+    state.crc = state.crc(outcomes);
+}
+
+/// Outcome of a test in a run over all tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Outcome {
+    NotRun = 0,
+    Passed = 1,
+    Failed = 2,
+    Ignored = 3,
+    TimedOut = 4,
+    Reset = 5,
+}
+
+impl Outcome {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Outcome::Passed,
+            2 => Outcome::Failed,
+            3 => Outcome::Ignored,
+            4 => Outcome::TimedOut,
+            5 => Outcome::Reset,
+            _ => Outcome::NotRun,
+        }
+    }
+}
+
+/// Set the test module: number of tests, hash of the test names and the
+/// outcome table (one entry per test, in uninitialized RAM).
+pub fn set_module(n_tests: u16, names_hash: u32, outcomes: &'static mut [u8]) {
+    unsafe {
+        MODULE = (n_tests, names_hash);
+        TEST_OUTCOMES = outcomes;
+    }
+}
 
 pub fn activate() {
+    let (n_tests, names_hash) = unsafe { MODULE };
     unsafe {
-        TEST_SECRET = SECRET_NUMBER;
-        TEST_SUCCESSFUL = 0;
+        *ptr::addr_of_mut!(STATE) = State {
+            magic: MAGIC,
+            layout_version: LAYOUT_VERSION,
+            n_tests,
+            names_hash,
+            ..State::zeroed()
+        };
     }
+    update(|_, outcomes| outcomes.fill(Outcome::NotRun as u8));
+    #[cfg(feature = "buffered")]
+    crate::serial::buffer::reset_overflow();
 }
 pub fn deactivate() {
-    unsafe { TEST_SECRET = 0; }
+    unsafe { (*ptr::addr_of_mut!(STATE)).magic = 0; }
 }
 
+/// Whether a run over all tests is in progress.
+///
+/// A state left by a different firmware or with an invalid CRC is reported
+/// and discarded instead of resuming.
 pub fn is_active() -> bool {
-    unsafe { TEST_SECRET == SECRET_NUMBER }
+    if state().magic != MAGIC {
+        return false;
+    }
+    match rejection(state(), outcomes()) {
+        Some(reason) => {
+            deactivate();
+            report::state_rejected(reason);
+            false
+        },
+        None => true,
+    }
+}
+
+/// Reason to reject the state of a run, `None` if it can be resumed.
+fn rejection(state: &State, outcomes: &[u8]) -> Option<&'static str> {
+    let (n_tests, names_hash) = unsafe { MODULE };
+    if state.layout_version != LAYOUT_VERSION
+        || state.n_tests != n_tests
+        || state.names_hash != names_hash
+    {
+        Some("stale (different firmware)")
+    } else if state.crc != state.crc(outcomes) {
+        Some("corrupted")
+    } else {
+        None
+    }
 }
 
-pub fn get_next_test() -> u8 {
-    unsafe { TEST_NEXT }
+pub fn get_next_test() -> u16 {
+    state().next
 }
-pub fn set_next_test(index: u8) {
-    unsafe { TEST_NEXT = index; }
+pub fn set_next_test(index: u16) {
+    update(|state, _| state.next = index);
 }
 
-pub fn test_succeeded() {
-    unsafe { TEST_SUCCESSFUL += 1; }
+pub fn set_outcome(index: u16, outcome: Outcome) {
+    update(|_, outcomes| {
+        if let Some(entry) = outcomes.get_mut(index as usize) {
+            *entry = outcome as u8;
+        }
+    });
+}
+pub fn get_outcome(index: u16) -> Outcome {
+    Outcome::from_u8(outcomes().get(index as usize).copied().unwrap_or(0))
+}
+/// Number of tests with `outcome`.
+pub fn count(outcome: Outcome) -> u16 {
+    outcomes().iter().filter(|o| Outcome::from_u8(**o) == outcome).count() as u16
+}
+/// Whether all tests passed or were ignored.
+pub fn succeeded() -> bool {
+    outcomes().iter()
+        .all(|o| matches!(Outcome::from_u8(*o), Outcome::Passed | Outcome::Ignored))
+}
+
+pub fn test_started(index: u16) {
+    update(|state, _| {
+        state.running = index + 1;
+        state.timeout = 0;
+    });
+}
+/// Record the outcome of the test in progress.
+pub fn test_finished(outcome: Outcome) {
+    update(|state, outcomes| {
+        if let Some(index) = state.running.checked_sub(1) {
+            if let Some(entry) = outcomes.get_mut(index as usize) {
+                *entry = outcome as u8;
+            }
+        }
+        state.running = 0;
+    });
+}
+
+pub fn arm_timeout() {
+    update(|state, _| state.timeout = 1);
+}
+pub fn disarm_timeout() {
+    update(|state, _| state.timeout = 0);
+}
+
+/// Test that was still in progress when the device was reset and whether
+/// its watchdog was running.
+pub fn take_interrupted() -> Option<(u16, bool)> {
+    let state = state();
+    let index = state.running.checked_sub(1)?;
+    let timed_out = state.timeout != 0;
+    update(|state, _| state.running = 0);
+    Some((index, timed_out))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::boxed::Box;
+    use std::sync::{Mutex, MutexGuard};
+    use std::vec;
+    use super::*;
+
+    /* the state is global, tests must not run concurrently */
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    const N_TESTS: u16 = 3;
+    const NAMES_HASH: u32 = 0xC0FF_EE00;
+    const STALE: Option<&str> = Some("stale (different firmware)");
+    const CORRUPTED: Option<&str> = Some("corrupted");
+
+    fn outcome_table(n_tests: u16) -> &'static mut [u8] {
+        Box::leak(vec![0u8; n_tests as usize].into_boxed_slice())
+    }
+
+    /// Start a run with two finished tests.
+    fn start_run() -> MutexGuard<'static, ()> {
+        let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_module(N_TESTS, NAMES_HASH, outcome_table(N_TESTS));
+        activate();
+        set_outcome(0, Outcome::Passed);
+        set_outcome(1, Outcome::Failed);
+        set_next_test(2);
+        guard
+    }
+
+    /// Check the rejection reason, a rejected state is deactivated.
+    fn assert_rejected(reason: Option<&str>) {
+        assert_eq!(rejection(state(), outcomes()), reason);
+        assert!(!is_active());
+        assert_ne!(state().magic, MAGIC);
+        assert!(!is_active());
+    }
+
+    #[test]
+    fn intact_state_resumes() {
+        let _guard = start_run();
+        assert_eq!(rejection(state(), outcomes()), None);
+        assert!(is_active());
+        assert_eq!(get_next_test(), 2);
+        assert_eq!(get_outcome(0), Outcome::Passed);
+        assert_eq!(get_outcome(1), Outcome::Failed);
+        assert_eq!(get_outcome(2), Outcome::NotRun);
+    }
+
+    #[test]
+    fn flipped_state_byte_is_corrupted() {
+        let _guard = start_run();
+        unsafe { (*ptr::addr_of_mut!(STATE)).next ^= 0x04; }
+        assert_rejected(CORRUPTED);
+    }
+
+    #[test]
+    fn flipped_outcome_is_corrupted() {
+        let _guard = start_run();
+        unsafe { (*ptr::addr_of_mut!(TEST_OUTCOMES))[1] ^= 0x01; }
+        assert_rejected(CORRUPTED);
+    }
+
+    #[test]
+    fn different_number_of_tests_is_stale() {
+        let _guard = start_run();
+        set_module(N_TESTS + 1, NAMES_HASH, outcome_table(N_TESTS + 1));
+        assert_rejected(STALE);
+    }
+
+    #[test]
+    fn different_names_hash_is_stale() {
+        let _guard = start_run();
+        set_module(N_TESTS, NAMES_HASH ^ 1, outcome_table(N_TESTS));
+        assert_rejected(STALE);
+    }
+
+    #[test]
+    fn different_layout_version_is_stale() {
+        let _guard = start_run();
+        update(|state, _| state.layout_version = LAYOUT_VERSION - 1);
+        assert_rejected(STALE);
+    }
 }
-pub fn get_success_count() -> u8 {
-    unsafe { TEST_SUCCESSFUL }
-}
\ No newline at end of file
@@ -0,0 +1,59 @@
+//! ARM semihosting transport.
+//!
+//! Output uses `SYS_WRITE0`/`SYS_WRITEC`, user input `SYS_READC`. At the end of
+//! a run over all tests the result is reported with `SYS_EXIT`, so QEMU returns
+//! pass/fail to the shell.
+//!
+//! # Example
+//! ```ignore
+//! #[cortex_m_rt::entry]
+//! fn main() -> ! {
+//!     // Register semihosting as transport
+//!     bern_test::semihosting::init();
+//!     /*...*/
+//! }
+//! ```
+use core::ptr;
+use cortex_m_semihosting::{debug, syscall};
+use crate::transport::{self, Error, Transport};
+
+/// Semihosting console of the debugger.
+pub struct Semihosting;
+
+static mut SEMIHOSTING: Semihosting = Semihosting;
+
+/// Register semihosting as transport.
+pub fn init() {
+    transport::set_transport(unsafe { &mut *ptr::addr_of_mut!(SEMIHOSTING) });
+}
+
+/// Report the test result to the debugger, terminates a QEMU session.
+pub fn exit(success: bool) {
+    debug::exit(match success {
+        true => debug::EXIT_SUCCESS,
+        false => debug::EXIT_FAILURE,
+    });
+}
+
+impl Transport for Semihosting {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        /* SYS_WRITE0 needs a null terminated string, copy in chunks */
+        let mut chunk = [0u8; 65];
+        for (i, part) in bytes.split(|b| *b == 0).enumerate() {
+            /* null bytes cannot be part of a string */
+            if i > 0 {
+                unsafe { syscall!(WRITEC, &0u8 as *const u8); }
+            }
+            for p in part.chunks(chunk.len() - 1) {
+                chunk[..p.len()].copy_from_slice(p);
+                chunk[p.len()] = 0;
+                unsafe { syscall!(WRITE0, chunk.as_ptr()); }
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        Ok(unsafe { syscall!(READC) } as u8)
+    }
+}
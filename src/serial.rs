@@ -3,7 +3,7 @@
 //! Setup any serial interface for transport.
 //!
 //! # Example
-//! ```no_run
+//! ```ignore
 //! #[cortex_m_rt::entry]
 //! fn main() -> ! {
 //!     let mut board = Board::new();
@@ -25,75 +25,297 @@
 //!     /*...*/
 //! }
 //! ```
-use core::{fmt, mem};
+//!
+//! A flush function (e.g. waiting for the transmission to complete) can be set
+//! with [`Serial::set_flush`], it is called when the test output is flushed.
+//!
+//! Any embedded-hal serial peripheral can be used directly with the `hal`
+//! ([`hal`]) or `io` ([`io`]) feature.
+//!
+//! Access to the global serial instance is exclusive (see [`Serial::with`]).
+//! A critical section is only held to take the instance, so interrupts keep
+//! running while printing. The `critical-section` crate needs an
+//! implementation for the target (e.g. `cortex-m/critical-section-single-core`).
+//!
+//! With the `buffered` feature output is sent interrupt driven, see [`buffer`].
+//!
+//! A closure up to [`CLOSURE_CAPACITY`] bytes is stored internally. Larger
+//! closures (e.g. capturing a UART handle and a flush pin) can be placed in a
+//! user provided [`ClosureStorage`]:
+//! ```ignore
+//! static mut TX_STORAGE: ClosureStorage<256> = ClosureStorage::new();
+//!
+//! Serial::set_write_in(unsafe { &mut *addr_of_mut!(TX_STORAGE) }, move |b| {
+//!     /*...*/
+//! });
+//! ```
+#[cfg(feature = "hal")]
+pub mod hal;
+#[cfg(feature = "io")]
+pub mod io;
+#[cfg(feature = "buffered")]
+pub mod buffer;
+
+use core::{fmt, ptr};
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use crate::transport::{self, Transport};
 use nb::{block, Error::Other};
 use core::fmt::Write;
 
-/// Serial Errors.
-#[derive(Debug)]
-pub enum Error {
-    /// Error from peripheral.
-    Peripheral,
-    /// No function to send defined.
-    NoUplink,
-    /// No function to receive defined.
-    NoDownlink,
-    /// RX buffer overrun
-    BufferOverrun,
+pub use crate::transport::Error;
+
+/// Size of the internal storage for the serial write and read closures.
+pub const CLOSURE_CAPACITY: usize = 64;
+/// Alignment of a [`ClosureStorage`].
+pub const CLOSURE_ALIGN: usize = 8;
+
+/// Aligned storage for a serial write or read closure (or a peripheral).
+///
+/// Use a `static` storage if a closure does not fit into the internal storage
+/// of [`CLOSURE_CAPACITY`] bytes.
+#[repr(C, align(8))]
+pub struct ClosureStorage<const N: usize> {
+    data: [MaybeUninit<u8>; N],
+    /* drops the value stored with `replace` */
+    drop: Option<unsafe fn(*mut u8)>,
+}
+
+impl<const N: usize> ClosureStorage<N> {
+    /// Create empty storage.
+    pub const fn new() -> Self {
+        ClosureStorage {
+            data: [MaybeUninit::uninit(); N],
+            drop: None,
+        }
+    }
+
+    /// Drop the previously stored value (if stored with `replace`) and move
+    /// `value` into the storage.
+    ///
+    /// Nothing may refer to the previous value anymore.
+    #[cfg(any(feature = "hal", feature = "io"))]
+    pub(crate) fn replace<F>(&'static mut self, value: F) -> &'static mut F {
+        unsafe fn drop_value<F>(ptr: *mut u8) {
+            ptr::drop_in_place(ptr as *mut F);
+        }
+        if let Some(drop) = self.drop.take() {
+            unsafe { drop(self.data.as_mut_ptr() as *mut u8); }
+        }
+        self.drop = Some(drop_value::<F>);
+        self.store(value)
+    }
+
+    /// Move a closure into the storage.
+    ///
+    /// The size and alignment of the closure are checked at compile time.
+    pub(crate) fn store<F>(&'static mut self, f: F) -> &'static mut F {
+        let () = Fits::<F, N>::OK;
+        let f_ptr = self.data.as_mut_ptr() as *mut F;
+        unsafe {
+            f_ptr.write(f);
+            &mut *f_ptr
+        }
+    }
+}
+
+impl<const N: usize> Default for ClosureStorage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compile time check whether `F` fits into a storage of `N` bytes.
+struct Fits<F, const N: usize>(PhantomData<F>);
+
+impl<F, const N: usize> Fits<F, N> {
+    const OK: () = assert!(
+        mem::size_of::<F>() <= N && mem::align_of::<F>() <= CLOSURE_ALIGN,
+        "closure does not fit into serial closure storage"
+    );
 }
 
 static mut SERIAL: Serial = Serial {
     write: None,
     read: None,
+    flush: None,
 };
 
 #[doc(hidden)]
 pub struct Serial {
     write: Option<&'static mut dyn FnMut(u8) -> nb::Result<(), Error>>,
     read: Option<&'static mut dyn FnMut() -> nb::Result<u8, Error>>,
+    flush: Option<&'static mut dyn FnMut() -> nb::Result<(), Error>>,
 }
 
-// todo: interrupt driven read and write
 impl Serial {
     /// Set a serial write function (mandatory).
     ///
-    /// # Safety
-    /// We basically want to create a memory leak/unbounded lifetime, so we can
-    /// access a serial write function from anywhere. This is quite unsafe, but
-    /// at least `mem::transmute` checks that the buffer has the correct size.
-    // todo: critical section, reentrancy check
+    /// The closure is moved into an internal storage of [`CLOSURE_CAPACITY`]
+    /// bytes, a larger closure results in a compile time error (use
+    /// [`Self::set_write_in`] instead). A previously set write function is
+    /// dropped.
     pub fn set_write<F>(write: F)
         where F: FnMut(u8) -> nb::Result<(), Error> + 'static
     {
-        static mut TX: [u8; 4] = [0; 4];
-        unsafe {
-            TX = mem::transmute(&write);
-            let write_ptr = &mut *(TX.as_mut_ptr() as *mut F);
-            SERIAL.write = Some(write_ptr);
-        }
+        Self::with_cs(|ser| ser.store_write(write));
+    }
+
+    /// Set a serial write function stored in user provided storage.
+    ///
+    /// A previously set write function is dropped.
+    pub fn set_write_in<F, const N: usize>(storage: &'static mut ClosureStorage<N>, write: F)
+        where F: FnMut(u8) -> nb::Result<(), Error> + 'static
+    {
+        Self::with_cs(move |ser| {
+            ser.drop_write();
+            ser.write = Some(storage.store(write));
+        });
     }
 
     /// Set a serial read function.
     ///
-    /// # Safety
     /// see [`Self::set_write`]
     pub fn set_read<F>(read: F)
         where F: FnMut() -> nb::Result<u8, Error> + 'static
     {
-        static mut RX: [u8; 4] = [0; 4];
-        unsafe {
-            RX = mem::transmute(&read);
-            let read_ptr = &mut *(RX.as_mut_ptr() as *mut F);
-            SERIAL.read = Some(read_ptr);
+        Self::with_cs(|ser| ser.store_read(read));
+    }
+
+    /// Set a serial read function stored in user provided storage.
+    ///
+    /// see [`Self::set_write_in`]
+    pub fn set_read_in<F, const N: usize>(storage: &'static mut ClosureStorage<N>, read: F)
+        where F: FnMut() -> nb::Result<u8, Error> + 'static
+    {
+        Self::with_cs(move |ser| {
+            ser.drop_read();
+            ser.read = Some(storage.store(read));
+        });
+    }
+
+    /// Set a serial flush function (optional).
+    ///
+    /// see [`Self::set_write`]
+    pub fn set_flush<F>(flush: F)
+        where F: FnMut() -> nb::Result<(), Error> + 'static
+    {
+        Self::with_cs(|ser| ser.store_flush(flush));
+    }
+
+    /// Set a serial flush function stored in user provided storage.
+    ///
+    /// see [`Self::set_write_in`]
+    pub fn set_flush_in<F, const N: usize>(storage: &'static mut ClosureStorage<N>, flush: F)
+        where F: FnMut() -> nb::Result<(), Error> + 'static
+    {
+        Self::with_cs(move |ser| {
+            ser.drop_flush();
+            ser.flush = Some(storage.store(flush));
+        });
+    }
+
+    /// Set the write function in the internal storage.
+    fn store_write<F>(&mut self, write: F)
+        where F: FnMut(u8) -> nb::Result<(), Error> + 'static
+    {
+        static mut TX: ClosureStorage<CLOSURE_CAPACITY> = ClosureStorage::new();
+        self.drop_write();
+        self.write = Some(unsafe { (*ptr::addr_of_mut!(TX)).store(write) });
+    }
+
+    /// Set the read function in the internal storage.
+    fn store_read<F>(&mut self, read: F)
+        where F: FnMut() -> nb::Result<u8, Error> + 'static
+    {
+        static mut RX: ClosureStorage<CLOSURE_CAPACITY> = ClosureStorage::new();
+        self.drop_read();
+        self.read = Some(unsafe { (*ptr::addr_of_mut!(RX)).store(read) });
+    }
+
+    /// Set the flush function in the internal storage.
+    fn store_flush<F>(&mut self, flush: F)
+        where F: FnMut() -> nb::Result<(), Error> + 'static
+    {
+        static mut FLUSH: ClosureStorage<CLOSURE_CAPACITY> = ClosureStorage::new();
+        self.drop_flush();
+        self.flush = Some(unsafe { (*ptr::addr_of_mut!(FLUSH)).store(flush) });
+    }
+
+    /// Drop all serial functions, e.g. before the peripheral they refer to is
+    /// replaced.
+    #[cfg(any(feature = "hal", feature = "io"))]
+    fn drop_all(&mut self) {
+        self.drop_write();
+        self.drop_read();
+        self.drop_flush();
+    }
+
+    /// Drop the current write function in place so its storage can be reused.
+    fn drop_write(&mut self) {
+        if let Some(write) = self.write.take() {
+            unsafe { ptr::drop_in_place(write); }
         }
     }
 
+    /// Drop the current read function in place so its storage can be reused.
+    fn drop_read(&mut self) {
+        if let Some(read) = self.read.take() {
+            unsafe { ptr::drop_in_place(read); }
+        }
+    }
+
+    /// Drop the current flush function in place so its storage can be reused.
+    fn drop_flush(&mut self) {
+        if let Some(flush) = self.flush.take() {
+            unsafe { ptr::drop_in_place(flush); }
+        }
+    }
+
+    /// Run `f` with exclusive access to the global serial instance.
+    ///
+    /// The instance shares the lock of the transport (see
+    /// [`transport::with`]): interrupts are only disabled to take it and on
+    /// nested use `f` is not run and `None` is returned.
+    pub fn with<R, F>(f: F) -> Option<R>
+        where F: FnOnce(&mut Serial) -> R
+    {
+        transport::lock(|| f(unsafe { &mut *ptr::addr_of_mut!(SERIAL) }))
+    }
+
+    /// Run `f` with exclusive access within a critical section, the serial
+    /// functions may be in use by an interrupt (`buffered` feature).
+    fn with_cs<F>(f: F)
+        where F: FnOnce(&mut Serial)
+    {
+        critical_section::with(|_| Self::with(f));
+    }
+
+    /// Get the global serial instance.
+    ///
+    /// # Safety
+    /// The returned reference aliases the global instance, bypassing the
+    /// lock of [`Self::with`].
     pub unsafe fn steal() -> &'static mut Self {
-        &mut SERIAL
+        &mut *ptr::addr_of_mut!(SERIAL)
     }
 
     #[doc(hidden)]
     pub fn write(&mut self, byte: u8) -> nb::Result<(), Error> {
+        #[cfg(feature = "buffered")]
+        {
+            if self.write.is_none() {
+                return Err(Other(Error::NoUplink));
+            }
+            buffer::enqueue(self, byte);
+            Ok(())
+        }
+        #[cfg(not(feature = "buffered"))]
+        self.write_direct(byte)
+    }
+
+    /// Write a byte using the serial write function, bypassing any buffer.
+    pub(crate) fn write_direct(&mut self, byte: u8) -> nb::Result<(), Error> {
         match &mut self.write {
             Some(w) => (w)(byte),
             _ => Err(nb::Error::Other(Error::NoUplink)),
@@ -102,43 +324,58 @@ impl Serial {
 
     #[doc(hidden)]
     pub fn write_str(s: &str) {
-        let ser = unsafe { Serial::steal() };
-        ser.write_str(s).ok();
+        Self::with(|ser| ser.write_str(s).ok());
     }
 
     #[doc(hidden)]
     pub fn write_fmt(arg: fmt::Arguments) {
-        let ser = unsafe { Serial::steal() };
-        ser.write_fmt(arg).ok();
+        Self::with(|ser| ser.write_fmt(arg).ok());
+    }
+
+    /// Flush the peripheral using the serial flush function, if any.
+    pub(crate) fn flush_direct(&mut self) -> nb::Result<(), Error> {
+        match &mut self.flush {
+            Some(f) => (f)(),
+            _ => Ok(()),
+        }
     }
 
     #[doc(hidden)]
     pub fn read(&mut self) -> nb::Result<u8, Error> {
+        #[cfg(feature = "buffered")]
+        {
+            if self.read.is_none() {
+                return Err(Other(Error::NoDownlink));
+            }
+            buffer::dequeue()
+        }
+        #[cfg(not(feature = "buffered"))]
+        self.read_direct()
+    }
+
+    /// Read a byte using the serial read function, bypassing any buffer.
+    pub(crate) fn read_direct(&mut self) -> nb::Result<u8, Error> {
         match &mut self.read {
             Some(r) => (r)(),
             _ => Err(Other(Error::NoDownlink)),
         }
     }
+}
 
-    #[doc(hidden)]
-    pub fn readln(&mut self, buffer: &mut [u8]) -> nb::Result<usize, Error> {
-        if self.read.is_none() {
-            return Err(Other(Error::NoDownlink));
-        }
+impl Transport for Serial {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        bytes.iter()
+            .try_for_each(|b| block!(Serial::write(self, *b)))
+    }
 
-        for (i, item) in buffer.iter_mut().enumerate() {
-            let c = block!(self.read());
-            match c {
-                Ok(c) => match c {
-                    b'\n' | b'\r' => return Ok(i),
-                    b => {
-                        *item = b;
-                    },
-                },
-                Err(e) => return Err(Other(e)),
-            }
-        }
-        return Err(Other(Error::BufferOverrun));
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        Serial::read(self)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        #[cfg(feature = "buffered")]
+        buffer::flush_buffer(self);
+        block!(self.flush_direct())
     }
 }
 
@@ -0,0 +1,216 @@
+//! Ring buffers for interrupt driven serial transport.
+//!
+//! With the `buffered` feature [`sprint!`](crate::sprint) and
+//! [`sprintln!`](crate::sprintln) only enqueue bytes into a TX buffer. The
+//! buffer is drained from [`on_tx_interrupt`] and received bytes are pushed to
+//! the RX buffer from [`on_rx_interrupt`].
+//!
+//! The serial write and read functions must not block, but return
+//! `WouldBlock` if the peripheral is busy or no byte was received.
+//!
+//! The interrupt handlers do not wait for a print in progress, the buffers and
+//! serial functions are accessed within short critical sections instead.
+//!
+//! If the TX buffer is full, printing waits until there is room: the buffer is
+//! drained by polling the serial write function, so this works with
+//! interrupts masked or from an interrupt as well. Bytes the serial write
+//! function fails to send and received bytes that do not fit into the RX
+//! buffer are dropped and counted. The drop counters persist over a soft
+//! reset during a run over all tests and are reported in the test summary.
+//!
+//! # Example
+//! ```ignore
+//! #[interrupt]
+//! fn USART2() {
+//!     serial::buffer::on_rx_interrupt();
+//!     serial::buffer::on_tx_interrupt();
+//! }
+//! ```
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use super::Serial;
+
+/// Size of the TX ring buffer.
+pub const TX_BUFFER_SIZE: usize = 256;
+/// Size of the RX ring buffer.
+pub const RX_BUFFER_SIZE: usize = 128;
+
+/// Byte ring buffer with a single producer and a single consumer.
+struct RingBuffer<const N: usize> {
+    data: UnsafeCell<[u8; N]>,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+unsafe impl<const N: usize> Sync for RingBuffer<N> {}
+
+impl<const N: usize> RingBuffer<N> {
+    const fn new() -> Self {
+        RingBuffer {
+            data: UnsafeCell::new([0; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Add a byte, returns `false` if the buffer is full.
+    fn push(&self, byte: u8) -> bool {
+        let head = self.head.load(Ordering::Relaxed);
+        let next = (head + 1) % N;
+        if next == self.tail.load(Ordering::Acquire) {
+            return false;
+        }
+        unsafe { (*self.data.get())[head] = byte; }
+        self.head.store(next, Ordering::Release);
+        true
+    }
+
+    /// Look at the oldest byte without removing it.
+    fn peek(&self) -> Option<u8> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail == self.head.load(Ordering::Acquire) {
+            return None;
+        }
+        Some(unsafe { (*self.data.get())[tail] })
+    }
+
+    /// Remove the oldest byte.
+    fn pop(&self) -> Option<u8> {
+        let byte = self.peek()?;
+        let tail = self.tail.load(Ordering::Relaxed);
+        self.tail.store((tail + 1) % N, Ordering::Release);
+        Some(byte)
+    }
+
+}
+
+static TX: RingBuffer<TX_BUFFER_SIZE> = RingBuffer::new();
+static RX: RingBuffer<RX_BUFFER_SIZE> = RingBuffer::new();
+
+/* these must be put in a linker section that does get initialized */
+#[link_section = ".uninit"]
+static mut TX_DROPPED: u32 = 0;
+#[link_section = ".uninit"]
+static mut RX_DROPPED: u32 = 0;
+
+/// Drain the TX buffer until the serial write function would block.
+///
+/// Call from the serial TX interrupt.
+pub fn on_tx_interrupt() {
+    critical_section::with(|_| drain(unsafe { Serial::steal() }));
+}
+
+/// Move received bytes into the RX buffer until the serial read function
+/// would block.
+///
+/// Call from the serial RX interrupt.
+pub fn on_rx_interrupt() {
+    critical_section::with(|_| {
+        let ser = unsafe { Serial::steal() };
+        while let Ok(byte) = ser.read_direct() {
+            if !RX.push(byte) {
+                count_dropped(unsafe { &mut *core::ptr::addr_of_mut!(RX_DROPPED) });
+            }
+        }
+    });
+}
+
+/// Enqueue a byte for transmission, waits while the buffer is full.
+pub(crate) fn enqueue(ser: &mut Serial, byte: u8) {
+    while !TX.push(byte) {
+        /* make room without relying on the TX interrupt */
+        critical_section::with(|_| drain(ser));
+    }
+    /* start transmission, the rest is sent from the interrupt */
+    critical_section::with(|_| drain(ser));
+}
+
+/// Get the oldest received byte.
+pub(crate) fn dequeue() -> nb::Result<u8, super::Error> {
+    RX.pop().ok_or(nb::Error::WouldBlock)
+}
+
+/// Send all bytes in the TX buffer, blocking.
+pub fn flush() {
+    Serial::with(flush_buffer);
+}
+
+pub(crate) fn flush_buffer(ser: &mut Serial) {
+    /* the TX interrupt may drain concurrently */
+    while critical_section::with(|_| {
+        drain(ser);
+        TX.peek().is_some()
+    }) {}
+}
+
+fn drain(ser: &mut Serial) {
+    while let Some(byte) = TX.peek() {
+        match ser.write_direct(byte) {
+            Ok(_) => { TX.pop(); },
+            Err(nb::Error::WouldBlock) => break,
+            /* byte cannot be sent, drop it */
+            Err(nb::Error::Other(_)) => {
+                TX.pop();
+                count_dropped(unsafe { &mut *core::ptr::addr_of_mut!(TX_DROPPED) });
+            },
+        }
+    }
+}
+
+fn count_dropped(counter: &mut u32) {
+    *counter = counter.saturating_add(1);
+}
+
+/// Reset the drop counters.
+pub(crate) fn reset_overflow() {
+    unsafe {
+        TX_DROPPED = 0;
+        RX_DROPPED = 0;
+    }
+}
+
+/// Number of bytes the serial write function failed to send.
+pub fn tx_dropped() -> u32 {
+    unsafe { TX_DROPPED }
+}
+
+/// Number of bytes dropped because the RX buffer was full.
+pub fn rx_dropped() -> u32 {
+    unsafe { RX_DROPPED }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RingBuffer;
+
+    #[test]
+    fn ring_buffer_full_and_empty() {
+        let buffer = RingBuffer::<4>::new();
+        assert_eq!(buffer.peek(), None);
+        assert_eq!(buffer.pop(), None);
+        assert!(buffer.push(1));
+        assert!(buffer.push(2));
+        assert!(buffer.push(3));
+        /* one slot stays free to tell full from empty */
+        assert!(!buffer.push(4));
+        assert_eq!(buffer.peek(), Some(1));
+        assert_eq!(buffer.pop(), Some(1));
+        assert!(buffer.push(4));
+        assert_eq!(buffer.pop(), Some(2));
+        assert_eq!(buffer.pop(), Some(3));
+        assert_eq!(buffer.pop(), Some(4));
+        assert_eq!(buffer.pop(), None);
+    }
+
+    #[test]
+    fn ring_buffer_wraps_around() {
+        let buffer = RingBuffer::<4>::new();
+        for i in 0..10u8 {
+            assert!(buffer.push(i));
+            assert!(buffer.push(i + 100));
+            assert_eq!(buffer.pop(), Some(i));
+            assert_eq!(buffer.pop(), Some(i + 100));
+        }
+        assert_eq!(buffer.pop(), None);
+    }
+}
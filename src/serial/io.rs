@@ -0,0 +1,99 @@
+//! embedded-io serial adapter (embedded-hal 1.0).
+//!
+//! # Example
+//! ```ignore
+//! #[cortex_m_rt::entry]
+//! fn main() -> ! {
+//!     let mut board = Board::new();
+//!     let vcp = board.vcp.take().unwrap();
+//!
+//!     // Set serial uplink and downlink
+//!     serial::io::set_serial(vcp);
+//!     /*...*/
+//! }
+//! ```
+use core::ptr;
+use embedded_io::{Read, ReadReady, Write, WriteReady};
+use super::{ClosureStorage, Error, Serial, CLOSURE_CAPACITY};
+
+/// Use a serial peripheral for uplink and downlink.
+///
+/// The peripheral is flushed together with the test output. It is moved into
+/// an internal storage of [`CLOSURE_CAPACITY`] bytes, use [`set_serial_in`]
+/// for larger peripherals.
+pub fn set_serial<S>(serial: S)
+    where S: Write + WriteReady + Read + ReadReady + 'static
+{
+    static mut PERIPHERAL: ClosureStorage<CLOSURE_CAPACITY> = ClosureStorage::new();
+    set_serial_in(unsafe { &mut *ptr::addr_of_mut!(PERIPHERAL) }, serial);
+}
+
+/// Use a serial peripheral stored in user provided storage for uplink and
+/// downlink.
+///
+/// A peripheral previously stored in `storage` is dropped.
+pub fn set_serial_in<S, const N: usize>(storage: &'static mut ClosureStorage<N>, serial: S)
+    where S: Write + WriteReady + Read + ReadReady + 'static
+{
+    Serial::with_cs(move |ser| {
+        /* no function may refer to the previous peripheral when it is dropped */
+        ser.drop_all();
+        /* uplink, downlink and flush share the peripheral, no access outlives
+         * the call to the closure */
+        let serial: *mut S = storage.replace(serial);
+        ser.store_write(move |b| write(unsafe { &mut *serial }, b));
+        ser.store_flush(move || flush(unsafe { &mut *serial }));
+        ser.store_read(move || read(unsafe { &mut *serial }));
+    });
+}
+
+/// Use split serial halves for uplink and downlink.
+///
+/// The TX half is moved into an internal storage of [`CLOSURE_CAPACITY`]
+/// bytes.
+pub fn set_split<TX, RX>(tx: TX, mut rx: RX)
+    where TX: Write + WriteReady + 'static,
+          RX: Read + ReadReady + 'static,
+{
+    static mut TX_PERIPHERAL: ClosureStorage<CLOSURE_CAPACITY> = ClosureStorage::new();
+    Serial::with_cs(move |ser| {
+        ser.drop_all();
+        /* write and flush share the TX half */
+        let tx: *mut TX = unsafe { (*ptr::addr_of_mut!(TX_PERIPHERAL)).replace(tx) };
+        ser.store_write(move |b| write(unsafe { &mut *tx }, b));
+        ser.store_flush(move || flush(unsafe { &mut *tx }));
+        ser.store_read(move || read(&mut rx));
+    });
+}
+
+/* `embedded_io` reads and writes block, only access the peripheral when ready */
+fn write<W: Write + WriteReady>(tx: &mut W, byte: u8) -> nb::Result<(), Error> {
+    match tx.write_ready() {
+        Ok(true) => (),
+        Ok(false) => return Err(nb::Error::WouldBlock),
+        Err(_) => return Err(nb::Error::Other(Error::Peripheral)),
+    }
+    match tx.write(&[byte]) {
+        Ok(0) => Err(nb::Error::WouldBlock),
+        Ok(_) => Ok(()),
+        Err(_) => Err(nb::Error::Other(Error::Peripheral)),
+    }
+}
+
+fn flush<W: Write>(tx: &mut W) -> nb::Result<(), Error> {
+    tx.flush().map_err(|_| nb::Error::Other(Error::Peripheral))
+}
+
+fn read<R: Read + ReadReady>(rx: &mut R) -> nb::Result<u8, Error> {
+    match rx.read_ready() {
+        Ok(true) => (),
+        Ok(false) => return Err(nb::Error::WouldBlock),
+        Err(_) => return Err(nb::Error::Other(Error::Peripheral)),
+    }
+    let mut byte = [0u8; 1];
+    match rx.read(&mut byte) {
+        Ok(0) => Err(nb::Error::WouldBlock),
+        Ok(_) => Ok(byte[0]),
+        Err(_) => Err(nb::Error::Other(Error::Peripheral)),
+    }
+}
@@ -0,0 +1,45 @@
+//! Host transport using standard output and input (`std` feature).
+//!
+//! Used by default on the host, so a test module can be run natively before
+//! flashing.
+use std::io::{self, Read, Write};
+use crate::transport::{Error, Transport};
+
+/// Standard output and input of the host process.
+pub struct Stdio;
+
+impl Transport for Stdio {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        io::stdout().write_all(bytes).map_err(|_| Error::Peripheral)
+    }
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        let mut byte = [0u8; 1];
+        match io::stdin().read(&mut byte) {
+            Ok(0) => Err(nb::Error::Other(Error::NoDownlink)),
+            Ok(_) => Ok(byte[0]),
+            Err(_) => Err(nb::Error::Other(Error::Peripheral)),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        io::stdout().flush().map_err(|_| Error::Peripheral)
+    }
+}
+
+/// Run a test and catch a panic, the panic is reported from the panic hook.
+#[doc(hidden)]
+pub fn run_catching<F: FnOnce()>(f: F) {
+    crate::flush();
+    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+}
+
+/// Report panics with the test runner instead of the default panic message.
+///
+/// `panicked` is called with the panic info and the panic message.
+#[doc(hidden)]
+pub fn set_panic_hook(panicked: fn(&dyn core::fmt::Display, &dyn core::fmt::Display)) {
+    std::panic::set_hook(std::boxed::Box::new(move |info| {
+        panicked(info, &info.payload_as_str().unwrap_or(""))
+    }));
+}
@@ -0,0 +1,194 @@
+//! Transport abstraction.
+//!
+//! All test output is written to the registered [`Transport`], user input for
+//! interactive mode is read from it. If no transport is registered, the serial
+//! interface is used (`serial` feature).
+//!
+//! # Example
+//! ```ignore
+//! struct Usb { /*...*/ }
+//!
+//! impl Transport for Usb {
+//!     fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+//!         /*...*/
+//!     }
+//! }
+//!
+//! #[cortex_m_rt::entry]
+//! fn main() -> ! {
+//!     let usb = cortex_m::singleton!(: Usb = Usb::new()).unwrap();
+//!     transport::set_transport(usb);
+//!     /*...*/
+//! }
+//! ```
+use core::{fmt, ptr};
+use core::sync::atomic::{AtomicBool, Ordering};
+use nb::block;
+
+/// Transport Errors.
+#[derive(Debug)]
+pub enum Error {
+    /// Error from peripheral.
+    Peripheral,
+    /// No function to send defined.
+    NoUplink,
+    /// No function to receive defined.
+    NoDownlink,
+    /// RX buffer overrun
+    BufferOverrun,
+}
+
+/// Byte transport for test output and user input.
+pub trait Transport {
+    /// Write all bytes, blocking.
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error>;
+
+    /// Read one byte if available (optional).
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        Err(nb::Error::Other(Error::NoDownlink))
+    }
+
+    /// Send any pending output, blocking (optional).
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+static mut TRANSPORT: Option<&'static mut dyn Transport> = None;
+/* set while the transport is in use, only tested and set within a critical
+ * section */
+static IN_USE: AtomicBool = AtomicBool::new(false);
+static PANIC_RELEASED: AtomicBool = AtomicBool::new(false);
+static mut NESTED_DROPPED: u32 = 0;
+
+/// Register the transport used for test output and user input.
+pub fn set_transport(transport: &'static mut dyn Transport) {
+    critical_section::with(move |_| unsafe {
+        *ptr::addr_of_mut!(TRANSPORT) = Some(transport);
+    });
+}
+
+/// Run `f` with exclusive access to the transport.
+///
+/// Interrupts are only disabled to take the transport, `f` (e.g. a blocking
+/// write of a line) runs with interrupts enabled. On nested use (e.g. printing
+/// from the panic handler while already printing) or if there is no transport
+/// `f` is not run and `None` is returned instead of deadlocking.
+pub fn with<R, F>(f: F) -> Option<R>
+    where F: FnOnce(&mut dyn Transport) -> R
+{
+    lock(|| match unsafe { &mut *ptr::addr_of_mut!(TRANSPORT) } {
+        Some(transport) => Some(f(*transport)),
+        None => with_default(f),
+    }).flatten()
+}
+
+/// Run `f` holding the lock of the transport, which is also the lock of the
+/// global serial instance (`serial` feature).
+pub(crate) fn lock<R, F>(f: F) -> Option<R>
+    where F: FnOnce() -> R
+{
+    let taken = critical_section::with(|_| {
+        if IN_USE.load(Ordering::Acquire) {
+            unsafe { NESTED_DROPPED = NESTED_DROPPED.saturating_add(1); }
+            return false;
+        }
+        IN_USE.store(true, Ordering::Release);
+        true
+    });
+    if !taken {
+        return None;
+    }
+    let result = f();
+    IN_USE.store(false, Ordering::Release);
+    Some(result)
+}
+
+/// Fallback if no transport was registered.
+fn with_default<R, F>(f: F) -> Option<R>
+    where F: FnOnce(&mut dyn Transport) -> R
+{
+    #[cfg(feature = "std")]
+    return Some(f(&mut crate::stdio::Stdio));
+    #[cfg(all(feature = "serial", not(feature = "std")))]
+    /* the lock is already held */
+    return Some(f(unsafe { crate::serial::Serial::steal() }));
+    #[cfg(not(any(feature = "serial", feature = "std")))]
+    {
+        let _ = f;
+        None
+    }
+}
+
+fn has_transport() -> bool {
+    cfg!(any(feature = "serial", feature = "std")) ||
+        critical_section::with(|_| unsafe { (*ptr::addr_of!(TRANSPORT)).is_some() })
+}
+
+/// Release the transport if it was in use when a panic occurred, so the panic
+/// message can be printed.
+///
+/// Only releases once, in case the transport itself panics.
+///
+/// # Safety
+/// Call only from the panic handler, the interrupted access must never
+/// resume.
+#[doc(hidden)]
+pub unsafe fn release_on_panic() {
+    critical_section::with(|_| {
+        if !PANIC_RELEASED.load(Ordering::Acquire) {
+            PANIC_RELEASED.store(true, Ordering::Release);
+            IN_USE.store(false, Ordering::Release);
+        }
+    });
+}
+
+/// Number of accesses dropped because of nested use (of the transport or the
+/// global serial instance).
+pub fn nested_dropped() -> u32 {
+    critical_section::with(|_| unsafe { NESTED_DROPPED })
+}
+
+/// Adapter to use `fmt::Write` on a transport.
+pub(crate) struct Writer<'a>(pub(crate) &'a mut dyn Transport);
+
+impl fmt::Write for Writer<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+#[doc(hidden)]
+pub fn write_str(s: &str) {
+    with(|t| t.write(s.as_bytes()).ok());
+}
+
+#[doc(hidden)]
+pub fn write_fmt(arg: fmt::Arguments) {
+    with(|t| fmt::Write::write_fmt(&mut Writer(t), arg).ok());
+}
+
+#[doc(hidden)]
+pub fn flush() {
+    with(|t| t.flush().ok());
+}
+
+/// Read a line into `buffer`, blocking.
+///
+/// The transport is only locked while reading a single byte.
+#[doc(hidden)]
+pub fn readln(buffer: &mut [u8]) -> Result<usize, Error> {
+    if !has_transport() {
+        return Err(Error::NoDownlink);
+    }
+    for (i, item) in buffer.iter_mut().enumerate() {
+        let c = block!(with(|t| t.read()).unwrap_or(Err(nb::Error::WouldBlock)))?;
+        match c {
+            b'\n' | b'\r' => return Ok(i),
+            b => {
+                *item = b;
+            },
+        }
+    }
+    Err(Error::BufferOverrun)
+}
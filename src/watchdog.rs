@@ -0,0 +1,76 @@
+//! Per-test timeout.
+//!
+//! A test with a timeout (`#[timeout_ms = N]` on the test or
+//! `#[bern_test::tests(timeout_ms = N)]` as module default) starts the
+//! registered watchdog before it runs and stops it afterwards. On expiry
+//! either:
+//! - the watchdog resets the device: after reboot the test is reported as
+//!   `FAILED (timeout)` (any reset while the watchdog runs is reported as
+//!   timeout) and a run over all tests continues with the next test
+//! - or a timer interrupt calls [`expired`], which reports the test right
+//!   away; the device must be reset afterwards
+//!
+//! # Example
+//! ```ignore
+//! bern_test::watchdog::set_hooks(
+//!     |timeout_ms| unsafe { WATCHDOG.start(timeout_ms.millis()) },
+//!     || unsafe { WATCHDOG.stop() },
+//! );
+//! ```
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::{report, run_all, transport};
+use crate::run_all::Outcome;
+
+/// Start (timeout in ms) and stop hooks.
+type Hooks = (fn(u32), fn());
+
+static mut HOOKS: Option<Hooks> = None;
+/* set while a test with a timeout runs in this boot */
+static ARMED: AtomicBool = AtomicBool::new(false);
+
+/// Set the watchdog hooks, `start` is called with the timeout in ms.
+pub fn set_hooks(start: fn(u32), stop: fn()) {
+    critical_section::with(|_| unsafe { *ptr::addr_of_mut!(HOOKS) = Some((start, stop)) });
+}
+
+fn hooks() -> Option<Hooks> {
+    critical_section::with(|_| unsafe { *ptr::addr_of!(HOOKS) })
+}
+
+/// Start the watchdog for the current test.
+#[doc(hidden)]
+pub fn start(timeout_ms: u32) {
+    run_all::arm_timeout();
+    ARMED.store(true, Ordering::SeqCst);
+    if let Some((start, _)) = hooks() {
+        start(timeout_ms);
+    }
+}
+
+/// Stop the watchdog if a test started it.
+#[doc(hidden)]
+pub fn stop() {
+    if !ARMED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    if let Some((_, stop)) = hooks() {
+        stop();
+    }
+    run_all::disarm_timeout();
+}
+
+/// The timeout of the current test expired, report the test as failed.
+///
+/// Call from the watchdog (early warning) or timer interrupt, then reset the
+/// device so a run over all tests continues with the next test.
+pub fn expired() {
+    if !ARMED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    run_all::test_finished(Outcome::TimedOut);
+    /* the interrupted test never resumes */
+    unsafe { transport::release_on_panic(); }
+    report::test_timed_out();
+    transport::flush();
+}
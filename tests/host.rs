@@ -0,0 +1,113 @@
+//! Runs the `host` example test module natively and checks its report.
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+/* builds share a target directory */
+static BUILD: Mutex<()> = Mutex::new(());
+
+/// Build the `host` example with `features`, returns the path of a copy of
+/// the binary.
+fn build(features: &str) -> PathBuf {
+    let _lock = BUILD.lock().unwrap();
+    let target_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("host");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--quiet", "--example", "host", "--no-default-features", "--features", features])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let binary = target_dir.join(format!("host-{}", features.replace(',', "-")));
+    std::fs::copy(target_dir.join("debug/examples/host"), &binary).unwrap();
+    binary
+}
+
+/// Run the example with `input` on stdin, returns stdout and exit code.
+fn run(features: &str, input: &str) -> (String, i32) {
+    let mut child = Command::new(build(features))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (String::from_utf8(output.stdout).unwrap(), output.status.code().unwrap())
+}
+
+#[test]
+fn autorun() {
+    let (stdout, code) = run("std,autorun", "");
+    assert!(stdout.contains("running 7 tests"), "{}", stdout);
+    assert!(stdout.contains("test tests::passes ... ok"));
+    assert!(stdout.contains("test tests::panics ... FAILED"));
+    assert!(stdout.contains("test tests::should_panic ... ok"));
+    assert!(stdout.contains("test tests::expected_message ... ok"));
+    assert!(stdout.contains("test tests::expected_location ... FAILED"));
+    assert!(stdout.contains("panic message: `unrelated`"));
+    assert!(stdout.contains("test tests::returns_error ... FAILED"));
+    assert!(stdout.contains("test tests::ignored ... ignored"));
+    assert!(stdout.contains("test result: FAILED. 3 passed; 3 failed; 1 ignored"));
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn select_passing_test() {
+    let (stdout, code) = run("std", "0\n");
+    assert!(stdout.contains("[a] run all tests"), "{}", stdout);
+    assert!(stdout.contains("test tests::passes ... ok"));
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn select_panicking_test() {
+    let (stdout, code) = run("std", "1\n");
+    assert!(stdout.contains("test tests::panics ... FAILED"), "{}", stdout);
+    assert!(stdout.contains("left: 4"));
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn select_should_panic_test() {
+    let (stdout, code) = run("std", "2\n");
+    assert!(stdout.contains("test tests::should_panic ... ok"), "{}", stdout);
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn select_out_of_range() {
+    let (stdout, code) = run("std", "99\n0\n");
+    assert!(stdout.contains("Error: Test index out of range, select [0..6]:"), "{}", stdout);
+    assert!(stdout.contains("test tests::passes ... ok"));
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn end_of_input() {
+    let (stdout, code) = run("std", "");
+    assert!(stdout.contains("Error: No downlink provided"), "{}", stdout);
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn select_run_all() {
+    let (stdout, code) = run("std", "a\n");
+    assert!(stdout.contains("test result: FAILED. 3 passed; 3 failed; 1 ignored"), "{}", stdout);
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn json_events() {
+    let (stdout, code) = run("std,autorun,json", "");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines.iter().all(|l| l.starts_with('{') && l.ends_with('}')), "{}", stdout);
+    let started = lines.iter()
+        .position(|l| l.contains("\"event\": \"started\", \"name\": \"tests::ignored\""))
+        .unwrap();
+    assert!(lines[started + 1].contains("\"name\": \"tests::ignored\", \"event\": \"ignored\""));
+    assert!(lines.last().unwrap().contains("\"event\": \"failed\", \"passed\": 3, \"failed\": 3, \"ignored\": 1"));
+    assert_eq!(code, 1);
+}